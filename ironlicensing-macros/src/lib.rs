@@ -0,0 +1,202 @@
+//! Procedural macros for the IronLicensing Rust SDK.
+//!
+//! These macros are re-exported from `ironlicensing` when the `macros` feature
+//! is enabled and should be used through that crate.
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+use std::collections::HashMap;
+use syn::{parse_macro_input, Data, DeriveInput, Expr, Fields, Ident, ItemFn, LitStr, Token};
+
+struct RequiresFeatureArgs {
+    feature: Expr,
+    fallback: Option<Expr>,
+}
+
+impl Parse for RequiresFeatureArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let feature: Expr = input.parse()?;
+        let mut fallback = None;
+
+        if input.parse::<Option<Token![,]>>()?.is_some() && !input.is_empty() {
+            let name: Ident = input.parse()?;
+            if name != "fallback" {
+                return Err(syn::Error::new(name.span(), "expected `fallback = <expr>`"));
+            }
+            input.parse::<Token![=]>()?;
+            fallback = Some(input.parse()?);
+            input.parse::<Option<Token![,]>>()?;
+        }
+
+        Ok(Self { feature, fallback })
+    }
+}
+
+/// Require a licensed feature before running the annotated function.
+///
+/// The feature is a string key or a typed key from `#[derive(FeatureKey)]`.
+///
+/// Without a fallback the function must return a `Result` whose error type
+/// implements `From<ironlicensing::LicenseError>`:
+///
+/// ```ignore
+/// #[requires_feature("premium")]
+/// fn export_pdf() -> ironlicensing::Result<()> {
+///     Ok(())
+/// }
+/// ```
+///
+/// With `fallback = <expr>` the expression is returned instead when the
+/// feature is missing or the global client is not initialized:
+///
+/// ```ignore
+/// #[requires_feature("premium", fallback = Vec::new())]
+/// fn premium_reports() -> Vec<Report> {
+///     load_reports()
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn requires_feature(args: TokenStream, item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(args as RequiresFeatureArgs);
+    let mut func = parse_macro_input!(item as ItemFn);
+
+    let feature = &args.feature;
+    let check = match &args.fallback {
+        Some(fallback) => quote! {
+            if !::ironlicensing::has_feature(#feature).unwrap_or(false) {
+                return #fallback;
+            }
+        },
+        None => quote! {
+            ::ironlicensing::require_feature(#feature)?;
+        },
+    };
+
+    func.block.stmts.insert(0, syn::parse_quote!(#check));
+
+    quote!(#func).into()
+}
+
+/// Implement `ironlicensing::FeatureKey` for a fieldless enum.
+///
+/// Each variant's key is its name in snake_case unless overridden with
+/// `#[ironlicensing(key = "...")]`. Also implements `AsRef<str>`, so variants can
+/// be passed anywhere the SDK takes a feature key.
+///
+/// ```ignore
+/// #[derive(Clone, Copy, FeatureKey)]
+/// enum Features {
+///     Premium,
+///     #[ironlicensing(key = "cloud-sync")]
+///     CloudSync,
+/// }
+/// ```
+#[proc_macro_derive(FeatureKey, attributes(ironlicensing))]
+pub fn derive_feature_key(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match feature_key_impl(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn feature_key_impl(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let name = &input.ident;
+    let Data::Enum(data) = &input.data else {
+        return Err(syn::Error::new_spanned(name, "FeatureKey can only be derived for enums"));
+    };
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(&input.generics, "FeatureKey enums cannot be generic"));
+    }
+
+    let mut variants = Vec::new();
+    let mut keys = Vec::new();
+    let mut seen: HashMap<String, &Ident> = HashMap::new();
+
+    for variant in &data.variants {
+        if !matches!(variant.fields, Fields::Unit) {
+            return Err(syn::Error::new_spanned(variant, "FeatureKey variants cannot have fields"));
+        }
+
+        let mut key = LitStr::new(&snake_case(&variant.ident.to_string()), variant.ident.span());
+        for attr in variant.attrs.iter().filter(|a| a.path().is_ident("ironlicensing")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("key") {
+                    key = meta.value()?.parse()?;
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `key = \"...\"`"))
+                }
+            })?;
+        }
+
+        if let Some(other) = seen.insert(key.value(), &variant.ident) {
+            return Err(syn::Error::new_spanned(
+                &key,
+                format!("feature key \"{}\" is also used by `{}`", key.value(), other),
+            ));
+        }
+        variants.push(&variant.ident);
+        keys.push(key);
+    }
+
+    Ok(quote! {
+        impl ::ironlicensing::FeatureKey for #name {
+            const ALL: &'static [Self] = &[#(Self::#variants),*];
+
+            fn key(self) -> &'static str {
+                match self {
+                    #(Self::#variants => #keys,)*
+                }
+            }
+        }
+
+        impl ::std::convert::AsRef<str> for #name {
+            fn as_ref(&self) -> &str {
+                ::ironlicensing::FeatureKey::key(*self)
+            }
+        }
+    })
+}
+
+/// Convert a variant name like `CloudSync` to `cloud_sync`.
+///
+/// An acronym stays one word, so `HTTPProxy` becomes `http_proxy`: a word
+/// starts at a capital after a lowercase letter or digit, or at the last
+/// capital of a run followed by a lowercase letter.
+fn snake_case(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut out = String::with_capacity(name.len() + 4);
+    for (i, &c) in chars.iter().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            let prev = chars[i - 1];
+            let next_is_lower = chars.get(i + 1).is_some_and(|n| n.is_lowercase());
+            if prev.is_lowercase() || prev.is_ascii_digit() || (prev.is_uppercase() && next_is_lower) {
+                out.push('_');
+            }
+        }
+        out.extend(c.to_lowercase());
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::snake_case;
+
+    #[test]
+    fn splits_words() {
+        assert_eq!(snake_case("Export"), "export");
+        assert_eq!(snake_case("CloudSync"), "cloud_sync");
+        assert_eq!(snake_case("Export2Pdf"), "export2_pdf");
+    }
+
+    #[test]
+    fn keeps_acronyms_together() {
+        assert_eq!(snake_case("HTTPProxy"), "http_proxy");
+        assert_eq!(snake_case("ExportPDF"), "export_pdf");
+        assert_eq!(snake_case("SSO"), "sso");
+        assert_eq!(snake_case("PDFExportV2"), "pdf_export_v2");
+    }
+}
@@ -0,0 +1,270 @@
+use crate::error::Result;
+use crate::storage::{self, Storage};
+use crate::transport::{EventsDelivery, Transport};
+use chrono::{SecondsFormat, Utc};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// Queued events that trigger a background flush.
+const BATCH_SIZE: usize = 20;
+
+/// Queued events kept while offline; the oldest are dropped beyond this.
+const MAX_QUEUED_EVENTS: usize = 1000;
+
+/// Wait before the first background retry after a failed flush,
+/// doubled on each further failure.
+const RETRY_BACKOFF: Duration = Duration::from_secs(30);
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(60 * 60);
+
+/// Automatic event names.
+pub(crate) const TRIAL_STARTED: &str = "trial_started";
+pub(crate) const TIERS_VIEWED: &str = "tiers_viewed";
+pub(crate) const CHECKOUT_STARTED: &str = "checkout_started";
+pub(crate) const LICENSE_ACTIVATED: &str = "license_activated";
+
+/// A tracked event waiting to be sent.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct AnalyticsEvent {
+    /// Unique per event so the server can drop duplicates from retried batches.
+    id: String,
+    name: String,
+    timestamp: String,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    properties: HashMap<String, serde_json::Value>,
+}
+
+/// Build event properties from key-value pairs.
+pub(crate) fn properties<const N: usize>(pairs: [(&str, serde_json::Value); N]) -> HashMap<String, serde_json::Value> {
+    pairs.into_iter().map(|(k, v)| (k.to_string(), v)).collect()
+}
+
+/// Retry state after failed flushes, so an offline client doesn't keep
+/// hitting the network in the background.
+#[derive(Default)]
+struct Backoff {
+    failures: u32,
+    until: Option<Instant>,
+}
+
+/// Opt-in usage events, queued until they are sent to the server in batches.
+///
+/// With offline caching the queue is a file shared by every process of the
+/// app, holding one JSON event per line. Tracking an event appends to it;
+/// sent events are removed by re-reading and rewriting it under its lock, so
+/// events other processes append meanwhile are kept. Without offline caching
+/// events are queued in memory.
+pub(crate) struct Analytics {
+    enabled: AtomicBool,
+    storage: Storage,
+    path: Option<PathBuf>,
+    /// The queue when there is no file.
+    memory: Mutex<Vec<AnalyticsEvent>>,
+    /// Events this process knows are queued, to decide when to flush. Other
+    /// processes appending to the file aren't counted.
+    queued: AtomicUsize,
+    flushing: AtomicBool,
+    backoff: Mutex<Backoff>,
+}
+
+impl Analytics {
+    pub fn new(enabled: bool, storage: Storage, path: Option<PathBuf>) -> Self {
+        let queued = match &path {
+            Some(path) if enabled => decode(fs::read(path).ok()).len(),
+            // Don't leave events behind from before the user opted out.
+            Some(path) => {
+                let _ = storage::remove(path);
+                0
+            }
+            None => 0,
+        };
+
+        Self {
+            enabled: AtomicBool::new(enabled),
+            storage,
+            path,
+            memory: Mutex::new(Vec::new()),
+            queued: AtomicUsize::new(queued),
+            flushing: AtomicBool::new(false),
+            backoff: Mutex::new(Backoff::default()),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Acquire)
+    }
+
+    /// Turn tracking on or off. Turning it off discards queued events.
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Release);
+        if !enabled {
+            self.memory.lock().clear();
+            self.queued.store(0, Ordering::Release);
+            if let Some(path) = &self.path {
+                let _ = storage::remove(path);
+            }
+        }
+    }
+
+    /// Queue an event. Returns true if enough events are queued to send a batch.
+    pub fn track(&self, name: &str, properties: HashMap<String, serde_json::Value>) -> bool {
+        if !self.is_enabled() {
+            return false;
+        }
+
+        let event = AnalyticsEvent {
+            id: Uuid::new_v4().to_string(),
+            name: name.to_string(),
+            timestamp: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+            properties,
+        };
+
+        match &self.path {
+            Some(path) => {
+                let _ = self.storage.append(path, &encode(std::slice::from_ref(&event)));
+                // Trim the file only once it holds twice the cap, so an
+                // offline client rewrites it once per MAX_QUEUED_EVENTS events.
+                if self.queued.fetch_add(1, Ordering::AcqRel) + 1 >= 2 * MAX_QUEUED_EVENTS {
+                    self.remove(&[]);
+                }
+            }
+            None => {
+                let mut memory = self.memory.lock();
+                memory.push(event);
+                let overflow = memory.len().saturating_sub(MAX_QUEUED_EVENTS);
+                memory.drain(..overflow);
+                self.queued.store(memory.len(), Ordering::Release);
+            }
+        }
+        self.queued.load(Ordering::Acquire) >= BATCH_SIZE
+    }
+
+    /// Check if any events are waiting to be sent, by this or another process.
+    pub fn has_pending(&self) -> bool {
+        match &self.path {
+            Some(path) => fs::metadata(path).is_ok_and(|m| m.len() > 0),
+            None => !self.memory.lock().is_empty(),
+        }
+    }
+
+    /// Send queued events in batches. A batch the server rejects is
+    /// dropped. If a batch can't be sent, it and everything after it stay
+    /// queued, and background flushes back off until a later retry.
+    ///
+    /// Only one process sends a shared queue at a time; if another is
+    /// already sending it, this returns without sending anything.
+    pub fn flush(&self, transport: &Transport) -> Result<()> {
+        let _lock = match &self.path {
+            Some(path) => match self.storage.try_lock(&flush_lock_path(path)) {
+                Ok(None) => return Ok(()),
+                lock => lock.ok().flatten(),
+            },
+            None => None,
+        };
+
+        loop {
+            let batch = self.next_batch();
+            if batch.is_empty() || !self.is_enabled() {
+                return Ok(());
+            }
+
+            match transport.send_events(&batch) {
+                Ok(EventsDelivery::Sent | EventsDelivery::Rejected) => {
+                    *self.backoff.lock() = Backoff::default();
+                }
+                Err(e) => {
+                    let mut backoff = self.backoff.lock();
+                    let delay = RETRY_BACKOFF.saturating_mul(2u32.saturating_pow(backoff.failures));
+                    backoff.failures = backoff.failures.saturating_add(1);
+                    backoff.until = Some(Instant::now() + delay.min(MAX_RETRY_BACKOFF));
+                    return Err(e);
+                }
+            }
+
+            let ids: Vec<&str> = batch.iter().map(|e| e.id.as_str()).collect();
+            self.remove(&ids);
+        }
+    }
+
+    /// Mark a background flush as started. Returns false if one is
+    /// already running, or if recent flushes failed and it's too soon to retry.
+    pub fn begin_flush(&self) -> bool {
+        if self.backoff.lock().until.is_some_and(|until| Instant::now() < until) {
+            return false;
+        }
+        !self.flushing.swap(true, Ordering::AcqRel)
+    }
+
+    pub fn end_flush(&self) {
+        self.flushing.store(false, Ordering::Release);
+    }
+
+    /// The oldest queued events, read fresh from the file if there is one.
+    fn next_batch(&self) -> Vec<AnalyticsEvent> {
+        let queue = match &self.path {
+            Some(path) => decode(fs::read(path).ok()),
+            None => self.memory.lock().clone(),
+        };
+        queue.into_iter().take(BATCH_SIZE).collect()
+    }
+
+    /// Remove events by ID and trim the queue to its cap. The file is
+    /// re-read under its lock so events other processes appended are kept.
+    fn remove(&self, ids: &[&str]) {
+        let keep = |queue: &mut Vec<AnalyticsEvent>| {
+            queue.retain(|e| !ids.contains(&e.id.as_str()));
+            let overflow = queue.len().saturating_sub(MAX_QUEUED_EVENTS);
+            queue.drain(..overflow);
+        };
+
+        match &self.path {
+            Some(path) => {
+                let _ = self.storage.update(path, |data| {
+                    let mut queue = decode(data);
+                    keep(&mut queue);
+                    self.queued.store(queue.len(), Ordering::Release);
+                    (!queue.is_empty()).then(|| encode(&queue))
+                });
+            }
+            None => {
+                let mut memory = self.memory.lock();
+                keep(&mut memory);
+                self.queued.store(memory.len(), Ordering::Release);
+            }
+        }
+    }
+}
+
+/// Lock held by the process sending a shared queue file.
+fn flush_lock_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".flush");
+    PathBuf::from(name)
+}
+
+fn encode(events: &[AnalyticsEvent]) -> Vec<u8> {
+    let mut data = Vec::new();
+    for event in events {
+        if let Ok(line) = serde_json::to_vec(event) {
+            data.extend_from_slice(&line);
+            data.push(b'\n');
+        }
+    }
+    data
+}
+
+/// Parse a queue file, skipping lines that don't parse, such as one cut
+/// short by a crash mid-append.
+fn decode(data: Option<Vec<u8>>) -> Vec<AnalyticsEvent> {
+    let data = data.unwrap_or_default();
+    String::from_utf8_lossy(&data)
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
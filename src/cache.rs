@@ -0,0 +1,125 @@
+use crate::error::{LicenseError, Result};
+use crate::storage::Storage;
+use crate::transport::{TiersFetch, Transport};
+use crate::types::ProductTier;
+use chrono::Utc;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CachedTiers {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    etag: Option<String>,
+    fetched_at: i64,
+    tiers: Vec<ProductTier>,
+}
+
+/// Product tiers cached in memory and, optionally, on disk.
+pub(crate) struct TierCache {
+    storage: Storage,
+    path: Option<PathBuf>,
+    ttl: Duration,
+    entry: RwLock<Option<CachedTiers>>,
+    refreshing: AtomicBool,
+}
+
+impl TierCache {
+    pub fn new(storage: Storage, path: Option<PathBuf>, ttl: Duration) -> Self {
+        let entry = path
+            .as_ref()
+            .and_then(|p| fs::read_to_string(p).ok())
+            .and_then(|s| serde_json::from_str(&s).ok());
+
+        Self {
+            storage,
+            path,
+            ttl,
+            entry: RwLock::new(entry),
+            refreshing: AtomicBool::new(false),
+        }
+    }
+
+    /// Cached tiers younger than the TTL.
+    pub fn fresh(&self) -> Option<Vec<ProductTier>> {
+        let ttl = self.ttl.as_secs() as i64;
+        self.entry
+            .read()
+            .as_ref()
+            .filter(|e| Utc::now().timestamp() - e.fetched_at < ttl)
+            .map(|e| e.tiers.clone())
+    }
+
+    /// Cached tiers regardless of age.
+    pub fn stale(&self) -> Option<Vec<ProductTier>> {
+        self.entry.read().as_ref().map(|e| e.tiers.clone())
+    }
+
+    /// Fetch tiers from the server, sending the cached ETag so an unchanged
+    /// list only costs a 304.
+    pub fn revalidate(&self, transport: &Transport) -> Result<Vec<ProductTier>> {
+        let etag = self.entry.read().as_ref().and_then(|e| e.etag.clone());
+
+        let entry = match transport.fetch_tiers(etag.as_deref())? {
+            TiersFetch::Modified { tiers, etag } => CachedTiers {
+                etag,
+                fetched_at: Utc::now().timestamp(),
+                tiers,
+            },
+            TiersFetch::NotModified => match self.entry.read().clone() {
+                Some(entry) => CachedTiers {
+                    fetched_at: Utc::now().timestamp(),
+                    ..entry
+                },
+                // A 304 without a cached copy means the server and cache disagree;
+                // retry unconditionally.
+                None => return self.revalidate_unconditionally(transport),
+            },
+        };
+
+        let tiers = entry.tiers.clone();
+        self.store(entry);
+        Ok(tiers)
+    }
+
+    /// Mark a background revalidation as started.
+    /// Returns false if one is already running.
+    pub fn begin_refresh(&self) -> bool {
+        !self.refreshing.swap(true, Ordering::AcqRel)
+    }
+
+    pub fn end_refresh(&self) {
+        self.refreshing.store(false, Ordering::Release);
+    }
+
+    fn revalidate_unconditionally(&self, transport: &Transport) -> Result<Vec<ProductTier>> {
+        match transport.fetch_tiers(None)? {
+            TiersFetch::Modified { tiers, etag } => {
+                self.store(CachedTiers {
+                    etag,
+                    fetched_at: Utc::now().timestamp(),
+                    tiers: tiers.clone(),
+                });
+                Ok(tiers)
+            }
+            // Nothing to be unmodified against; an empty list would read as
+            // "no tiers", so fail unless a copy was cached meanwhile.
+            TiersFetch::NotModified => self.stale().ok_or_else(|| {
+                LicenseError::Api("Server returned 304 Not Modified for an unconditional tiers request".to_string())
+            }),
+        }
+    }
+
+    fn store(&self, entry: CachedTiers) {
+        if let Some(path) = &self.path {
+            if let Ok(json) = serde_json::to_vec(&entry) {
+                let _ = self.storage.write(path, &json);
+            }
+        }
+        *self.entry.write() = Some(entry);
+    }
+}
@@ -0,0 +1,117 @@
+use crate::error::{LicenseError, Result};
+use reqwest::Url;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Path the payment provider redirects back to.
+const CALLBACK_PATH: &str = "/callback";
+
+/// Interval between accept attempts while waiting for the redirect.
+const ACCEPT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+const RESPONSE_BODY: &str = "<!DOCTYPE html><html><body>\
+<p>Checkout complete. You can close this window and return to the application.</p>\
+</body></html>";
+
+/// Query parameters received on the loopback redirect.
+#[derive(Debug, Clone, Default)]
+pub struct CheckoutRedirect {
+    pub session_id: Option<String>,
+    pub status: Option<String>,
+}
+
+/// A short-lived localhost listener that receives the payment provider's
+/// redirect after checkout, in the style of OAuth desktop flows.
+pub struct CheckoutCallback {
+    listener: TcpListener,
+    redirect_url: String,
+}
+
+impl CheckoutCallback {
+    /// Bind a listener on an ephemeral loopback port.
+    pub fn bind() -> Result<Self> {
+        let listener = TcpListener::bind(("127.0.0.1", 0))?;
+        listener.set_nonblocking(true)?;
+        let port = listener.local_addr()?.port();
+
+        Ok(Self {
+            listener,
+            redirect_url: format!("http://127.0.0.1:{}{}", port, CALLBACK_PATH),
+        })
+    }
+
+    /// The URL to pass as the checkout redirect target.
+    pub fn redirect_url(&self) -> &str {
+        &self.redirect_url
+    }
+
+    /// Block until the redirect for `session_id` arrives or `timeout` elapses.
+    /// Returns `Ok(None)` on timeout.
+    ///
+    /// Redirects for other sessions and connections that fail or send
+    /// nothing, such as browser preconnects, are answered or dropped and
+    /// waiting continues.
+    pub fn wait(&self, session_id: &str, timeout: Duration) -> Result<Option<CheckoutRedirect>> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            match self.listener.accept() {
+                Ok((stream, _)) => {
+                    if let Ok(Some(redirect)) = Self::handle(stream, session_id) {
+                        return Ok(Some(redirect));
+                    }
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {}
+                Err(e) => return Err(LicenseError::Io(e)),
+            }
+
+            if Instant::now() >= deadline {
+                return Ok(None);
+            }
+            thread::sleep(ACCEPT_POLL_INTERVAL);
+        }
+    }
+
+    fn handle(mut stream: TcpStream, session_id: &str) -> Result<Option<CheckoutRedirect>> {
+        stream.set_nonblocking(false)?;
+        stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+
+        let mut request_line = String::new();
+        BufReader::new(&stream).read_line(&mut request_line)?;
+
+        // "GET /callback?session_id=...&status=... HTTP/1.1"
+        let target = request_line.split_whitespace().nth(1).unwrap_or_default();
+        let url = Url::parse(&format!("http://127.0.0.1{}", target)).ok();
+
+        let redirect = url.filter(|u| u.path() == CALLBACK_PATH).map(|url| {
+            let mut redirect = CheckoutRedirect::default();
+            for (key, value) in url.query_pairs() {
+                match key.as_ref() {
+                    "session_id" | "sessionId" => redirect.session_id = Some(value.into_owned()),
+                    "status" => redirect.status = Some(value.into_owned()),
+                    _ => {}
+                }
+            }
+            redirect
+        });
+
+        // Anything on the loopback port can send a redirect; only accept the
+        // session that was started.
+        match redirect.filter(|r| r.session_id.as_deref() == Some(session_id)) {
+            Some(redirect) => {
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    RESPONSE_BODY.len(),
+                    RESPONSE_BODY
+                );
+                Ok(Some(redirect))
+            }
+            None => {
+                let _ = stream.write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+                Ok(None)
+            }
+        }
+    }
+}
@@ -1,40 +1,136 @@
+use crate::analytics::{self, Analytics};
+use crate::cache::TierCache;
+use crate::callback::CheckoutCallback;
 use crate::config::LicenseOptions;
 use crate::error::{LicenseError, Result};
+use crate::events::{EventBus, LicenseEvent};
+use crate::guard::FeatureGuard;
+use crate::key::LicenseKey;
+use crate::registry::{FeatureKey, FeatureRegistryReport};
+use crate::secrets::{EncryptedFileStore, SecretBackend, SecretStore};
+use crate::storage::Storage;
 use crate::transport::Transport;
-use crate::types::{CheckoutResult, Feature, License, LicenseResult, LicenseStatus, LicenseType, ProductTier};
+use crate::types::{
+    Activation, CheckoutResult, CheckoutSession, CheckoutStatus, FailureReason, Feature, FeatureSource, License, LicenseResult, LicenseStatus, LicenseType, PaymentState, ProductTier, ServerMessage,
+    TrialStatus, UpgradeOption,
+};
 use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use std::sync::mpsc::Receiver;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Interval between checkout session polls in `wait_for_checkout`.
+const CHECKOUT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Secret store entry holding the last validated license.
+const STORED_LICENSE: &str = "license";
+
+/// Secret store entry holding activated add-on licenses.
+const STORED_ADDONS: &str = "addons";
+
+/// Secret store entry holding the dismissal keys of dismissed server messages.
+const STORED_DISMISSED_MESSAGES: &str = "dismissed_messages";
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StoredLicense {
+    license_key: String,
+    license: License,
+}
 
 /// The main IronLicensing client.
 /// Thread-safe and can be shared across threads.
 pub struct LicenseClient {
     options: LicenseOptions,
-    transport: Transport,
+    transport: Arc<Transport>,
+    tiers: Arc<TierCache>,
+    analytics: Arc<Analytics>,
+    secrets: Option<Arc<dyn SecretStore>>,
     current_license: RwLock<Option<License>>,
+    /// Development override; always `None` without the `dev-override`
+    /// feature or in release builds.
+    dev_license: Option<License>,
     license_key: RwLock<Option<String>>,
+    addons: RwLock<Vec<StoredLicense>>,
+    /// License key and activation found by `validate` under a previous machine ID.
+    pending_transfer: RwLock<Option<(String, Activation)>>,
+    /// Messages from the server's latest response about the license.
+    messages: RwLock<Vec<ServerMessage>>,
+    dismissed_messages: RwLock<HashSet<String>>,
+    events: EventBus,
 }
 
 impl LicenseClient {
     /// Create a new LicenseClient with the given options.
+    /// Fails if the options don't pass [`LicenseOptions::build`].
     pub fn new(options: LicenseOptions) -> Result<Self> {
-        if options.public_key.is_empty() {
-            return Err(LicenseError::PublicKeyRequired);
-        }
-        if options.product_slug.is_empty() {
-            return Err(LicenseError::ProductSlugRequired);
-        }
+        let options = options.build()?;
 
-        let transport = Transport::new(&options);
+        let storage = Storage::new(&options);
+        let transport = Arc::new(Transport::new(&options, &storage));
+        let tiers_file = match (&options.pricing_country, &options.pricing_currency) {
+            (None, None) => "tiers.json".to_string(),
+            (country, currency) => format!(
+                "tiers-{}-{}.json",
+                country.as_deref().unwrap_or("any"),
+                currency.as_deref().unwrap_or("any")
+            ),
+        };
+        let tiers_path = options
+            .enable_offline_cache
+            .then(|| storage.product_dir().join(tiers_file));
+        let secrets = options
+            .enable_offline_cache
+            .then(|| Self::secret_store(&options, &storage))
+            .transpose()?;
+        let analytics_path = options
+            .enable_offline_cache
+            .then(|| storage.product_dir().join("analytics.jsonl"));
+        let analytics = Arc::new(Analytics::new(options.analytics, storage.clone(), analytics_path));
+        let tiers = Arc::new(TierCache::new(storage, tiers_path, options.tiers_cache_ttl));
+        let dismissed_messages: HashSet<String> = secrets
+            .as_ref()
+            .and_then(|s| s.get(STORED_DISMISSED_MESSAGES).ok().flatten())
+            .and_then(|data| serde_json::from_slice(&data).ok())
+            .unwrap_or_default();
+
+        #[cfg(all(feature = "dev-override", debug_assertions))]
+        let dev_license = crate::dev::load()?;
+        #[cfg(not(all(feature = "dev-override", debug_assertions)))]
+        let dev_license = None;
+        if dev_license.is_some() && options.debug {
+            println!("[IronLicensing] Using development license override; the server will not be contacted");
+        }
 
         if options.debug {
             println!("[IronLicensing] Client initialized");
         }
 
-        Ok(Self {
+        let client = Self {
             options,
             transport,
-            current_license: RwLock::new(None),
+            tiers,
+            analytics,
+            secrets,
+            current_license: RwLock::new(dev_license.clone()),
+            dev_license,
             license_key: RwLock::new(None),
-        })
+            addons: RwLock::new(Vec::new()),
+            pending_transfer: RwLock::new(None),
+            messages: RwLock::new(Vec::new()),
+            dismissed_messages: RwLock::new(dismissed_messages),
+            events: EventBus::new(),
+        };
+
+        // Send events queued while offline in a previous session.
+        if client.analytics.has_pending() {
+            client.flush_analytics_in_background();
+        }
+        Ok(client)
     }
 
     /// Create a new client with public key and product slug.
@@ -42,16 +138,76 @@ impl LicenseClient {
         Self::new(LicenseOptions::new(public_key, product_slug))
     }
 
+    /// Re-validate the license saved by a previous session, falling back to
+    /// the configured `license_key`. Returns `None` if there is neither.
+    /// Saved add-ons are loaded and re-validated too.
+    /// Licenses are saved when offline caching is enabled.
+    pub fn restore(&self) -> Option<LicenseResult> {
+        if let Some(result) = self.dev_override() {
+            return Some(result);
+        }
+
+        self.restore_addons();
+
+        let stored = self
+            .secrets
+            .as_ref()
+            .and_then(|s| s.get(STORED_LICENSE).ok().flatten())
+            .and_then(|data| serde_json::from_slice::<StoredLicense>(&data).ok())
+            .map(|s| s.license_key);
+
+        let key = stored.or_else(|| self.options.license_key.clone())?;
+        Some(self.validate(&key))
+    }
+
     /// Validate a license key.
+    /// Malformed keys are rejected locally without a network call.
+    ///
+    /// A valid license that the server reports as not activated on this
+    /// machine fails with [`FailureReason::NotActivatedOnThisMachine`]
+    /// and is not stored. Licenses the server sends no binding information
+    /// for are accepted. If this machine was activated under a previous
+    /// machine ID (same hardware, e.g. after an OS reinstall), the result's
+    /// `transferable_activation` is set; see [`transfer_activation`](Self::transfer_activation).
     pub fn validate(&self, license_key: &str) -> LicenseResult {
-        let result = self.transport.validate(license_key);
-        if result.valid {
-            if let Some(license) = &result.license {
-                *self.current_license.write() = Some(license.clone());
-                *self.license_key.write() = Some(license_key.to_string());
+        if let Some(result) = self.dev_override() {
+            return result;
+        }
+
+        let key = match self.parse_key(license_key) {
+            Ok(key) => key,
+            Err(e) => return LicenseResult::rejected(FailureReason::InvalidKey, e.to_string()),
+        };
+
+        let result = self.transport.validate(&key);
+        if result.valid && result.is_bound_to(self.machine_id()) == Some(false) {
+            self.log("License is not activated on this machine");
+            if self.license_key.read().as_deref() == Some(key.as_str()) {
+                self.clear_license();
+            }
+
+            // Without a per-unit fingerprint this machine can't be told apart
+            // from others of the same model, so nothing is transferable.
+            let transferable = self.transport.fingerprint().and_then(|fingerprint| {
+                result
+                    .transferable_activation
+                    .clone()
+                    .or_else(|| result.drifted_activation(self.machine_id(), fingerprint).cloned())
+            });
+            if let Some(activation) = &transferable {
+                self.log("Found an activation for this machine under a previous machine ID");
+                *self.pending_transfer.write() = Some((key.to_string(), activation.clone()));
             }
+
+            return LicenseResult {
+                valid: false,
+                error: Some(FailureReason::NotActivatedOnThisMachine.as_str().to_string()),
+                reason: Some(FailureReason::NotActivatedOnThisMachine),
+                transferable_activation: transferable,
+                ..result
+            };
         }
-        result
+        self.accept(result, Some(key.as_str()), LicenseEvent::Validated)
     }
 
     /// Activate a license key on this machine.
@@ -61,65 +217,266 @@ impl LicenseClient {
 
     /// Activate a license key with a custom machine name.
     pub fn activate_with_name(&self, license_key: &str, machine_name: Option<&str>) -> LicenseResult {
-        let result = self.transport.activate(license_key, machine_name);
-        if result.valid {
-            if let Some(license) = &result.license {
-                *self.current_license.write() = Some(license.clone());
-                *self.license_key.write() = Some(license_key.to_string());
-            }
+        if let Some(result) = self.dev_override() {
+            return result;
         }
+
+        let key = match self.parse_key(license_key) {
+            Ok(key) => key,
+            Err(e) => return LicenseResult::rejected(FailureReason::InvalidKey, e.to_string()),
+        };
+
+        let result = self.transport.activate(&key, machine_name);
+        let result = self.accept(result, Some(key.as_str()), LicenseEvent::Activated);
+        self.track(analytics::LICENSE_ACTIVATED, analytics::properties([("success", json!(result.valid))]));
         result
     }
 
+    /// The activation `validate` found for this machine under a previous
+    /// machine ID, if any.
+    pub fn transferable_activation(&self) -> Option<Activation> {
+        self.pending_transfer.read().as_ref().map(|(_, a)| a.clone())
+    }
+
+    /// Move the activation found by `validate` under a previous machine ID
+    /// to this machine, replacing it in a single server call. The server
+    /// may refuse with its own error, e.g. when transfer limits are reached.
+    pub fn transfer_activation(&self) -> LicenseResult {
+        let Some((license_key, from)) = self.pending_transfer.read().clone() else {
+            return LicenseResult::local_failure(FailureReason::NoTransferableActivation);
+        };
+        let key = match self.parse_key(&license_key) {
+            Ok(key) => key,
+            Err(e) => return LicenseResult::rejected(FailureReason::InvalidKey, e.to_string()),
+        };
+
+        let result = self.transport.transfer_activation(&key, &from.machine_id);
+        if result.valid {
+            *self.pending_transfer.write() = None;
+        }
+        self.accept(result, Some(key.as_str()), LicenseEvent::Activated)
+    }
+
     /// Deactivate the current license from this machine.
     pub fn deactivate(&self) -> bool {
         let key = self.license_key.read().clone();
         if let Some(key) = key {
             if self.transport.deactivate(&key) {
-                *self.current_license.write() = None;
-                *self.license_key.write() = None;
+                self.clear_license();
                 return true;
             }
         }
         false
     }
 
+    /// Activate an add-on license key alongside the primary license.
+    /// Its features are tagged with [`FeatureSource::AddOn`] and count
+    /// towards `has_feature` while inside their validity windows.
+    pub fn activate_addon(&self, license_key: &str) -> LicenseResult {
+        let key = match self.parse_key(license_key) {
+            Ok(key) => key,
+            Err(e) => return LicenseResult::rejected(FailureReason::InvalidKey, e.to_string()),
+        };
+
+        let result = self.transport.activate(&key, None);
+        let Some(license) = result.license.as_ref().filter(|_| result.valid) else {
+            return result;
+        };
+        if license.sandbox && self.options.reject_sandbox_licenses {
+            return LicenseResult::local_failure(FailureReason::SandboxLicenseRejected);
+        }
+
+        self.update_addons(|addons| {
+            addons.retain(|a| a.license_key != key.as_str());
+            addons.push(Self::addon(key.as_str(), license.clone()));
+        });
+        result
+    }
+
+    /// Deactivate an add-on license key from this machine.
+    pub fn deactivate_addon(&self, license_key: &str) -> bool {
+        let Ok(key) = LicenseKey::parse(license_key) else {
+            return false;
+        };
+        if !self.transport.deactivate(key.as_str()) {
+            return false;
+        }
+
+        self.update_addons(|addons| addons.retain(|a| a.license_key != key.as_str()));
+        true
+    }
+
+    /// Get the activated add-on licenses.
+    pub fn addons(&self) -> Vec<License> {
+        self.addons.read().iter().map(|a| a.license.clone()).collect()
+    }
+
     /// Start a trial for the given email.
     pub fn start_trial(&self, email: &str) -> LicenseResult {
-        let result = self.transport.start_trial(email);
-        if result.valid {
-            if let Some(license) = &result.license {
-                *self.current_license.write() = Some(license.clone());
-                *self.license_key.write() = Some(license.key.clone());
+        self.begin_trial(Some(email))
+    }
+
+    /// Start an anonymous trial bound to this machine.
+    pub fn start_anonymous_trial(&self) -> LicenseResult {
+        self.begin_trial(None)
+    }
+
+    /// Get the trial status for this machine from the server.
+    pub fn trial_status(&self) -> Result<TrialStatus> {
+        self.transport.trial_status()
+    }
+
+    /// Extend the current trial with a support-issued extension code.
+    pub fn extend_trial(&self, code: &str) -> LicenseResult {
+        if let Some(result) = self.dev_override() {
+            return result;
+        }
+
+        let key = self.license_key.read().clone();
+        let result = self.transport.extend_trial(key.as_deref(), code);
+        self.accept(result, None, LicenseEvent::TrialExtended)
+    }
+
+    /// Convert the current trial to a full license.
+    /// The existing activation on this machine is kept.
+    pub fn convert_trial(&self, license_key: &str) -> LicenseResult {
+        if let Some(result) = self.dev_override() {
+            return result;
+        }
+
+        let key = match self.parse_key(license_key) {
+            Ok(key) => key,
+            Err(e) => return LicenseResult::rejected(FailureReason::InvalidKey, e.to_string()),
+        };
+
+        let trial_key = self.license_key.read().clone();
+        let result = self.transport.convert_trial(trial_key.as_deref(), &key);
+        self.accept(result, Some(key.as_str()), LicenseEvent::TrialConverted)
+    }
+
+    /// Record a usage event, e.g. a feature being used.
+    /// Ignored unless analytics are enabled; see [`LicenseOptions::analytics`].
+    pub fn track_event(&self, name: &str, properties: HashMap<String, serde_json::Value>) {
+        self.track(name, properties);
+    }
+
+    /// Send queued usage events now, e.g. before the application exits.
+    /// Events that can't be sent stay queued, on disk when offline caching
+    /// is enabled, and are retried later. Events the server rejects are dropped.
+    pub fn flush_analytics(&self) -> Result<()> {
+        self.analytics.flush(&self.transport)
+    }
+
+    /// Check if usage events are currently being recorded.
+    pub fn analytics_enabled(&self) -> bool {
+        self.analytics.is_enabled()
+    }
+
+    /// Turn usage analytics on or off, e.g. from a privacy setting.
+    /// Turning them off discards events that haven't been sent yet.
+    pub fn set_analytics_enabled(&self, enabled: bool) {
+        self.analytics.set_enabled(enabled);
+    }
+
+    /// Server messages about the license that haven't been dismissed,
+    /// from the latest validation, activation or trial response.
+    pub fn pending_messages(&self) -> Vec<ServerMessage> {
+        let dismissed = self.dismissed_messages.read();
+        self.messages
+            .read()
+            .iter()
+            .filter(|m| !dismissed.contains(m.dismissal_key()))
+            .cloned()
+            .collect()
+    }
+
+    /// Dismiss a server message so `pending_messages` no longer returns it,
+    /// or any later message with the same dismiss key. Dismissals are saved
+    /// when offline caching is enabled, and forgotten once a successful
+    /// response no longer includes the message.
+    pub fn dismiss_message(&self, id: &str) {
+        let key = self
+            .messages
+            .read()
+            .iter()
+            .find(|m| m.id == id)
+            .map_or(id, |m| m.dismissal_key())
+            .to_string();
+
+        let dismissed = {
+            let mut dismissed = self.dismissed_messages.write();
+            if !dismissed.insert(key) {
+                return;
+            }
+            dismissed.clone()
+        };
+        self.save_dismissed_messages(&dismissed);
+    }
+
+    fn save_dismissed_messages(&self, dismissed: &HashSet<String>) {
+        if let Some(secrets) = &self.secrets {
+            let saved = serde_json::to_vec(dismissed)
+                .map_err(LicenseError::from)
+                .and_then(|data| secrets.set(STORED_DISMISSED_MESSAGES, &data));
+            if let Err(e) = saved {
+                self.log(&format!("Failed to save dismissed messages: {}", e));
             }
         }
-        result
+    }
+
+    /// Subscribe to license state changes.
+    ///
+    /// The receiver gets a [`LicenseEvent`] every time the client's license
+    /// state is mutated. Dropping the receiver unsubscribes.
+    pub fn subscribe(&self) -> Receiver<LicenseEvent> {
+        self.events.subscribe()
+    }
+
+    /// Subscribe to license state changes from async code.
+    #[cfg(feature = "tokio")]
+    pub fn subscribe_async(&self) -> tokio::sync::broadcast::Receiver<LicenseEvent> {
+        self.events.subscribe_async()
     }
 
     /// Check if a feature is available in the current license.
-    pub fn has_feature(&self, feature_key: &str) -> bool {
-        self.current_license
-            .read()
-            .as_ref()
-            .map(|l| l.has_feature(feature_key))
-            .unwrap_or(false)
+    /// Accepts string keys or typed [`FeatureKey`]s.
+    pub fn has_feature(&self, feature_key: impl AsRef<str>) -> bool {
+        let feature_key = feature_key.as_ref();
+        self.effective_features().iter().any(|f| f.key == feature_key)
+    }
+
+    /// Active features of the current license and add-ons plus everything
+    /// they imply, through both server-sent and locally declared implications.
+    /// Features outside their validity window are left out.
+    pub fn effective_features(&self) -> Vec<Feature> {
+        self.options.feature_hierarchy.expand(&self.all_features())
     }
 
     /// Require a feature to be available.
     /// Returns an error if the feature is not available.
-    pub fn require_feature(&self, feature_key: &str) -> Result<()> {
+    pub fn require_feature(&self, feature_key: impl AsRef<str>) -> Result<()> {
+        let feature_key = feature_key.as_ref();
         if !self.has_feature(feature_key) {
             return Err(LicenseError::FeatureRequired(feature_key.to_string()));
         }
         Ok(())
     }
 
-    /// Get a feature from the current license.
-    pub fn get_feature(&self, feature_key: &str) -> Option<Feature> {
-        self.current_license
-            .read()
-            .as_ref()
-            .and_then(|l| l.get_feature(feature_key).cloned())
+    /// Acquire a guard proving a feature is available in the current license.
+    pub fn feature_guard(&self, feature_key: impl AsRef<str>) -> Result<FeatureGuard> {
+        FeatureGuard::acquire_with(self, feature_key)
+    }
+
+    /// Get a feature from the current license, including implied features.
+    pub fn get_feature(&self, feature_key: impl AsRef<str>) -> Option<Feature> {
+        let feature_key = feature_key.as_ref();
+        let features = self.all_features();
+        self.options
+            .feature_hierarchy
+            .expand(&features)
+            .into_iter()
+            .find(|f| f.key == feature_key)
+            .or_else(|| features.into_iter().find(|f| f.key == feature_key))
     }
 
     /// Get the current license.
@@ -154,19 +511,508 @@ impl LicenseClient {
             .unwrap_or(false)
     }
 
+    /// Check if a build, given as a release date or version, is covered by
+    /// the current license's updates entitlement. False without a license.
+    pub fn is_version_allowed(&self, build_date_or_semver: &str) -> bool {
+        self.current_license
+            .read()
+            .as_ref()
+            .is_some_and(|l| l.is_version_allowed(build_date_or_semver))
+    }
+
+    /// Check if the subscription payment has failed and the license is in
+    /// its payment grace period.
+    pub fn is_in_dunning(&self) -> bool {
+        self.current_license
+            .read()
+            .as_ref()
+            .map(|l| l.is_in_dunning())
+            .unwrap_or(false)
+    }
+
+    /// Where the current subscription stands after a failed payment.
+    pub fn payment_state(&self) -> PaymentState {
+        self.current_license
+            .read()
+            .as_ref()
+            .map(|l| l.payment_state())
+            .unwrap_or_default()
+    }
+
+    /// When the current subscription will next renew, if it is set to renew.
+    pub fn renews_at(&self) -> Option<String> {
+        self.current_license
+            .read()
+            .as_ref()
+            .and_then(|l| l.renews_at().map(String::from))
+    }
+
+    /// Get a URL to the customer portal where the subscriber can update
+    /// payment details or cancel.
+    pub fn customer_portal_url(&self) -> Result<String> {
+        let key = self.license_key.read().clone().ok_or(LicenseError::NoLicense)?;
+        self.transport.customer_portal_url(&key)
+    }
+
     /// Get available product tiers for purchase.
+    /// Returns cached tiers if the server can't be reached, or an empty list
+    /// if there is nothing cached.
     pub fn get_tiers(&self) -> Vec<ProductTier> {
-        self.transport.get_tiers()
+        self.try_get_tiers().unwrap_or_default()
+    }
+
+    /// Get available product tiers for purchase.
+    /// Unlike [`get_tiers`](Self::get_tiers), returns an error if tiers could
+    /// not be fetched and none are cached, so an empty list means the product
+    /// has no tiers.
+    pub fn try_get_tiers(&self) -> Result<Vec<ProductTier>> {
+        let tiers = self.load_tiers()?;
+        self.track(analytics::TIERS_VIEWED, analytics::properties([("count", json!(tiers.len()))]));
+        Ok(tiers)
+    }
+
+    /// Tiers from the cache or the server, without recording a tier view.
+    fn load_tiers(&self) -> Result<Vec<ProductTier>> {
+        if let Some(tiers) = self.tiers.fresh() {
+            return Ok(tiers);
+        }
+
+        if self.options.stale_while_revalidate {
+            if let Some(tiers) = self.tiers.stale() {
+                self.refresh_tiers_in_background();
+                return Ok(tiers);
+            }
+        }
+
+        match self.tiers.revalidate(&self.transport) {
+            Ok(tiers) => Ok(tiers),
+            Err(e) => self.tiers.stale().ok_or(e),
+        }
+    }
+
+    /// Compare a feature registry with the features the server returns in
+    /// the product's tiers and the current license.
+    pub fn check_feature_registry<K: FeatureKey>(&self) -> Result<FeatureRegistryReport> {
+        let tiers = self.load_tiers()?;
+        let license = self.license();
+
+        let server_keys = tiers
+            .iter()
+            .flat_map(|t| &t.features)
+            .chain(license.iter().flat_map(|l| &l.features))
+            .map(|f| f.key.as_str());
+        Ok(FeatureRegistryReport::compare::<K>(server_keys))
+    }
+
+    /// Get the tier of the current license, if it is one of the available tiers.
+    pub fn current_tier(&self) -> Option<ProductTier> {
+        let tier_id = self.current_license.read().as_ref()?.tier_id.clone()?;
+        self.load_tiers().unwrap_or_default().into_iter().find(|t| t.id == tier_id)
+    }
+
+    /// Get the tiers the current license can move to, with the feature
+    /// difference against the current license.
+    pub fn upgrade_options(&self) -> Vec<UpgradeOption> {
+        let license = match self.license() {
+            Some(license) => license,
+            None => return vec![],
+        };
+
+        let tiers = self.load_tiers().unwrap_or_default();
+        let current = license
+            .tier_id
+            .as_ref()
+            .and_then(|id| tiers.iter().find(|t| &t.id == id))
+            .cloned();
+
+        tiers
+            .into_iter()
+            .filter(|t| Some(&t.id) != license.tier_id.as_ref())
+            .map(|t| UpgradeOption::compare(t, &license, current.as_ref(), &self.options.feature_hierarchy))
+            .collect()
+    }
+
+    /// Start a prorated checkout moving the current license to another tier.
+    pub fn start_upgrade(&self, tier_id: &str) -> CheckoutResult {
+        match self.license_key.read().clone() {
+            Some(key) => self.transport.start_upgrade(&key, tier_id),
+            None => CheckoutResult::failure("No license to upgrade"),
+        }
     }
 
     /// Start a checkout session for the specified tier.
     pub fn start_purchase(&self, tier_id: &str, email: &str) -> CheckoutResult {
-        self.transport.start_checkout(tier_id, email)
+        let result = self.transport.start_checkout(tier_id, email, None);
+        self.track_checkout(tier_id, &result);
+        result
+    }
+
+    /// Start a checkout session that redirects to `redirect_url` when finished.
+    /// Use with [`CheckoutCallback::redirect_url`] to receive the redirect locally.
+    pub fn start_purchase_with_redirect(&self, tier_id: &str, email: &str, redirect_url: &str) -> CheckoutResult {
+        let result = self.transport.start_checkout(tier_id, email, Some(redirect_url));
+        self.track_checkout(tier_id, &result);
+        result
+    }
+
+    /// Get the current state of a checkout session.
+    pub fn poll_checkout(&self, session_id: &str) -> Result<CheckoutSession> {
+        self.transport.checkout_status(session_id)
+    }
+
+    /// Poll a checkout session until it is no longer pending.
+    /// Returns `LicenseError::CheckoutTimeout` if it is still pending after `timeout`.
+    ///
+    /// Network and 5xx errors are retried until the deadline, and the last
+    /// one is returned if it is reached; other errors end the wait at once.
+    pub fn wait_for_checkout(&self, session_id: &str, timeout: Duration) -> Result<CheckoutSession> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let error = match self.poll_checkout(session_id) {
+                Ok(session) if !session.is_pending() => return Ok(session),
+                Ok(_) => None,
+                Err(e) if is_transient(&e) => Some(e),
+                Err(e) => return Err(e),
+            };
+
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(error.unwrap_or_else(|| LicenseError::CheckoutTimeout(session_id.to_string())));
+            }
+            thread::sleep(CHECKOUT_POLL_INTERVAL.min(deadline - now));
+        }
+    }
+
+    /// Wait for the payment provider to redirect to `callback`, then confirm
+    /// the session state with the server.
+    pub fn wait_for_checkout_redirect(
+        &self,
+        session_id: &str,
+        callback: &CheckoutCallback,
+        timeout: Duration,
+    ) -> Result<CheckoutSession> {
+        let deadline = Instant::now() + timeout;
+        let redirect = callback
+            .wait(session_id, timeout)?
+            .ok_or_else(|| LicenseError::CheckoutTimeout(session_id.to_string()))?;
+
+        // The redirect only tells us the user came back; the server is authoritative.
+        let remaining = || deadline.saturating_duration_since(Instant::now());
+        let mut session = match self.poll_checkout(session_id) {
+            Ok(session) => session,
+            Err(e) if is_transient(&e) => return self.wait_for_checkout(session_id, remaining()),
+            Err(e) => return Err(e),
+        };
+        if session.is_pending() {
+            if matches!(redirect.status.as_deref(), Some("canceled" | "cancelled")) {
+                session.status = CheckoutStatus::Canceled;
+                return Ok(session);
+            }
+            return self.wait_for_checkout(session_id, remaining());
+        }
+        Ok(session)
+    }
+
+    /// Wait for a checkout session to complete and activate the issued
+    /// license on this machine.
+    pub fn wait_for_checkout_and_activate(&self, session_id: &str, timeout: Duration) -> Result<LicenseResult> {
+        let session = self.wait_for_checkout(session_id, timeout)?;
+        if !session.is_completed() {
+            let error = session
+                .error
+                .unwrap_or_else(|| format!("Checkout {:?}", session.status).to_lowercase());
+            return Ok(LicenseResult::failure(error));
+        }
+
+        match session.license_key {
+            Some(key) => Ok(self.activate(&key)),
+            None => Err(LicenseError::Api("Checkout completed without a license key".to_string())),
+        }
+    }
+
+    /// Get the options this client was created with.
+    pub fn options(&self) -> &LicenseOptions {
+        &self.options
     }
 
     /// Get the machine ID used for activations.
     pub fn machine_id(&self) -> &str {
         self.transport.machine_id()
     }
+
+    fn parse_key(&self, license_key: &str) -> Result<LicenseKey> {
+        let key = LicenseKey::parse(license_key)?;
+        if self.options.verify_key_checksum && !key.has_valid_checksum() {
+            return Err(LicenseError::InvalidLicenseKey("checksum mismatch".to_string()));
+        }
+        Ok(key)
+    }
+
+    fn secret_store(options: &LicenseOptions, storage: &Storage) -> Result<Arc<dyn SecretStore>> {
+        Ok(match &options.secret_backend {
+            SecretBackend::EncryptedFile => Arc::new(EncryptedFileStore::new(storage.product_dir(), &options.product_slug)?),
+            #[cfg(all(target_os = "linux", feature = "secret-service"))]
+            SecretBackend::SecretService => {
+                let product = match options.environment.storage_namespace() {
+                    Some(namespace) => format!("{}@{}", options.product_slug, namespace),
+                    None => options.product_slug.clone(),
+                };
+                Arc::new(crate::secrets::SecretServiceStore::new(product))
+            }
+            SecretBackend::Custom(store) => Arc::clone(store),
+        })
+    }
+
+    fn log(&self, msg: &str) {
+        if self.options.debug {
+            println!("[IronLicensing] {}", msg);
+        }
+    }
+
+    /// The development license override as a result, if one is loaded. The
+    /// result is valid only for valid and trial statuses, so other states can
+    /// be exercised too.
+    fn dev_override(&self) -> Option<LicenseResult> {
+        self.dev_license.clone().map(|license| LicenseResult {
+            valid: matches!(license.status, LicenseStatus::Valid | LicenseStatus::Trial),
+            ..LicenseResult::success(license)
+        })
+    }
+
+    /// Keep a result's server messages and store its license if successful,
+    /// unless it's a sandbox license and the options reject those.
+    fn accept(&self, result: LicenseResult, license_key: Option<&str>, event: fn(License) -> LicenseEvent) -> LicenseResult {
+        self.receive_messages(&result);
+        let Some(license) = result.license.as_ref().filter(|_| result.valid) else {
+            return result;
+        };
+        if license.sandbox && self.options.reject_sandbox_licenses {
+            self.log("Rejected sandbox license");
+            return LicenseResult::local_failure(FailureReason::SandboxLicenseRejected);
+        }
+
+        self.store_license(license, license_key.unwrap_or(&license.key), event);
+        result
+    }
+
+    /// Replace the server messages with those of a server response.
+    /// Results without a license, e.g. network errors, keep the current ones.
+    /// A valid response lists every live message, so dismissals of messages
+    /// it no longer includes are dropped.
+    fn receive_messages(&self, result: &LicenseResult) {
+        if result.license.is_some() || !result.messages.is_empty() {
+            *self.messages.write() = result.messages.clone();
+        }
+        if !(result.valid && result.license.is_some()) {
+            return;
+        }
+
+        let pruned = {
+            let mut dismissed = self.dismissed_messages.write();
+            let before = dismissed.len();
+            dismissed.retain(|key| result.messages.iter().any(|m| m.dismissal_key() == key));
+            (dismissed.len() != before).then(|| dismissed.clone())
+        };
+        if let Some(dismissed) = pruned {
+            self.save_dismissed_messages(&dismissed);
+        }
+    }
+
+    fn store_license(&self, license: &License, license_key: &str, event: fn(License) -> LicenseEvent) {
+        let before = self.feature_keys();
+        let previous = self.current_license.write().replace(license.clone());
+        *self.license_key.write() = Some(license_key.to_string());
+
+        if let Some(secrets) = &self.secrets {
+            let stored = StoredLicense {
+                license_key: license_key.to_string(),
+                license: license.clone(),
+            };
+            let saved = serde_json::to_vec(&stored)
+                .map_err(LicenseError::from)
+                .and_then(|data| secrets.set(STORED_LICENSE, &data));
+            if let Err(e) = saved {
+                self.log(&format!("Failed to save license: {}", e));
+            }
+        }
+
+        self.events.emit(event(license.clone()));
+        self.events.emit_status_change(previous.as_ref(), Some(license));
+        self.events.emit_feature_changes(&before, &self.feature_keys());
+    }
+
+    /// Keys of the effective features, for change events.
+    fn feature_keys(&self) -> Vec<String> {
+        self.effective_features().into_iter().map(|f| f.key).collect()
+    }
+
+    /// Features of the current license followed by those of each add-on,
+    /// counting only licenses that are valid or in trial. A dev license
+    /// override may be loaded in any status.
+    fn all_features(&self) -> Vec<Feature> {
+        let mut features: Vec<Feature> = self
+            .current_license
+            .read()
+            .as_ref()
+            .filter(|l| matches!(l.status, LicenseStatus::Valid | LicenseStatus::Trial))
+            .map(|l| l.features.clone())
+            .unwrap_or_default();
+        features.extend(
+            self.addons
+                .read()
+                .iter()
+                .filter(|a| matches!(a.license.status, LicenseStatus::Valid | LicenseStatus::Trial))
+                .flat_map(|a| a.license.features.iter().cloned()),
+        );
+        features
+    }
+
+    /// An add-on entry with its features tagged with their source.
+    fn addon(license_key: &str, mut license: License) -> StoredLicense {
+        for feature in &mut license.features {
+            feature.source = FeatureSource::AddOn {
+                license_key: license_key.to_string(),
+            };
+        }
+        StoredLicense {
+            license_key: license_key.to_string(),
+            license,
+        }
+    }
+
+    /// Modify the add-ons, persist them and emit feature changes.
+    fn update_addons(&self, update: impl FnOnce(&mut Vec<StoredLicense>)) {
+        let before = self.feature_keys();
+
+        let addons = {
+            let mut addons = self.addons.write();
+            update(&mut addons);
+            addons.clone()
+        };
+
+        if let Some(secrets) = &self.secrets {
+            let saved = serde_json::to_vec(&addons)
+                .map_err(LicenseError::from)
+                .and_then(|data| secrets.set(STORED_ADDONS, &data));
+            if let Err(e) = saved {
+                self.log(&format!("Failed to save add-ons: {}", e));
+            }
+        }
+
+        self.events.emit_feature_changes(&before, &self.feature_keys());
+    }
+
+    /// Load saved add-ons, then refresh each from the server. Add-ons the
+    /// server can't be reached for keep their saved state; those it reports
+    /// as invalid, e.g. revoked or expired, or no longer activated on this
+    /// machine are dropped.
+    fn restore_addons(&self) {
+        let stored: Vec<StoredLicense> = self
+            .secrets
+            .as_ref()
+            .and_then(|s| s.get(STORED_ADDONS).ok().flatten())
+            .and_then(|data| serde_json::from_slice(&data).ok())
+            .unwrap_or_default();
+        if stored.is_empty() {
+            return;
+        }
+
+        let machine_id = self.machine_id();
+        let refreshed = stored
+            .into_iter()
+            .filter_map(|addon| {
+                let result = match LicenseKey::parse(&addon.license_key) {
+                    Ok(key) => self.transport.validate(&key),
+                    Err(_) => return Some(addon),
+                };
+                if result.is_bound_to(machine_id) == Some(false) {
+                    return None;
+                }
+                if !result.valid && result.reason != Some(FailureReason::Unreachable) {
+                    return None;
+                }
+                match result.license {
+                    Some(license) => Some(Self::addon(&addon.license_key, license)),
+                    None => Some(addon),
+                }
+            })
+            .collect();
+        self.update_addons(|addons| *addons = refreshed);
+    }
+
+    fn clear_license(&self) {
+        let before = self.feature_keys();
+        let previous = self.current_license.write().take();
+        *self.license_key.write() = None;
+        self.messages.write().clear();
+
+        if let Some(secrets) = &self.secrets {
+            if let Err(e) = secrets.delete(STORED_LICENSE) {
+                self.log(&format!("Failed to remove saved license: {}", e));
+            }
+        }
+
+        self.events.emit(LicenseEvent::Deactivated);
+        self.events.emit_status_change(previous.as_ref(), None);
+        self.events.emit_feature_changes(&before, &self.feature_keys());
+    }
+
+    fn begin_trial(&self, email: Option<&str>) -> LicenseResult {
+        if let Some(result) = self.dev_override() {
+            return result;
+        }
+
+        let result = self.transport.start_trial(email);
+        let result = self.accept(result, None, LicenseEvent::TrialStarted);
+        self.track(
+            analytics::TRIAL_STARTED,
+            analytics::properties([("success", json!(result.valid)), ("anonymous", json!(email.is_none()))]),
+        );
+        result
+    }
+
+    fn track_checkout(&self, tier_id: &str, result: &CheckoutResult) {
+        self.track(
+            analytics::CHECKOUT_STARTED,
+            analytics::properties([("tier_id", json!(tier_id)), ("success", json!(result.success))]),
+        );
+    }
+
+    fn track(&self, name: &str, properties: HashMap<String, serde_json::Value>) {
+        if self.analytics.track(name, properties) {
+            self.flush_analytics_in_background();
+        }
+    }
+
+    fn flush_analytics_in_background(&self) {
+        if !self.analytics.begin_flush() {
+            return;
+        }
+
+        let transport = Arc::clone(&self.transport);
+        let analytics = Arc::clone(&self.analytics);
+        thread::spawn(move || {
+            let _ = analytics.flush(&transport);
+            analytics.end_flush();
+        });
+    }
+
+    fn refresh_tiers_in_background(&self) {
+        if !self.tiers.begin_refresh() {
+            return;
+        }
+
+        let transport = Arc::clone(&self.transport);
+        let tiers = Arc::clone(&self.tiers);
+        thread::spawn(move || {
+            let _ = tiers.revalidate(&transport);
+            tiers.end_refresh();
+        });
+    }
 }
 
+/// Whether a failed request may succeed if retried: network errors and 5xx
+/// responses.
+fn is_transient(error: &LicenseError) -> bool {
+    matches!(error, LicenseError::Http(_) | LicenseError::Server { .. })
+}
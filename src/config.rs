@@ -1,5 +1,81 @@
+use crate::error::{LicenseError, Result};
+use crate::hierarchy::FeatureHierarchy;
+use crate::secrets::SecretBackend;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::time::Duration;
 
+/// Prefix for environment variables read by `LicenseOptions::from_env`.
+const ENV_PREFIX: &str = "IRONLICENSING_";
+
+/// Production API base URL.
+pub(crate) const LIVE_API_BASE_URL: &str = "https://api.ironlicensing.com";
+
+/// Sandbox API base URL.
+pub(crate) const SANDBOX_API_BASE_URL: &str = "https://sandbox.api.ironlicensing.com";
+
+/// Public key prefixes for live and test-mode keys.
+const LIVE_KEY_PREFIX: &str = "pk_live_";
+const TEST_KEY_PREFIX: &str = "pk_test_";
+
+/// The API environment the client talks to.
+///
+/// Each environment keeps its own on-disk state, so sandbox licenses,
+/// caches and machine IDs never mix with live ones.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Environment {
+    /// Production, for `pk_live_` keys.
+    #[default]
+    Live,
+    /// Test mode, for `pk_test_` keys. Licenses are marked as sandbox.
+    Sandbox,
+    /// A self-hosted or proxied API at the given base URL.
+    Custom(String),
+}
+
+impl Environment {
+    /// Parse `"live"` or `"sandbox"`.
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "live" | "production" => Some(Self::Live),
+            "sandbox" | "test" => Some(Self::Sandbox),
+            _ => None,
+        }
+    }
+
+    /// The API base URL for this environment.
+    pub fn api_base_url(&self) -> &str {
+        match self {
+            Self::Live => LIVE_API_BASE_URL,
+            Self::Sandbox => SANDBOX_API_BASE_URL,
+            Self::Custom(url) => url,
+        }
+    }
+
+    /// Check if this is the production environment.
+    pub fn is_live(&self) -> bool {
+        matches!(self, Self::Live)
+    }
+
+    /// Subdirectory of the storage root for this environment's state,
+    /// or `None` for live, which uses the root itself.
+    pub(crate) fn storage_namespace(&self) -> Option<String> {
+        match self {
+            Self::Live => None,
+            Self::Sandbox => Some("sandbox".to_string()),
+            Self::Custom(url) => {
+                let hash = Sha256::digest(url.as_bytes());
+                let hex: String = hash[..6].iter().map(|b| format!("{:02x}", b)).collect();
+                Some(format!("custom-{}", hex))
+            }
+        }
+    }
+}
+
 /// Configuration options for the LicenseClient.
 #[derive(Debug, Clone)]
 pub struct LicenseOptions {
@@ -7,18 +83,49 @@ pub struct LicenseOptions {
     pub public_key: String,
     /// Product slug identifier (required).
     pub product_slug: String,
+    /// API environment. Set with [`environment`](Self::environment) or
+    /// [`api_base_url`](Self::api_base_url).
+    pub environment: Environment,
     /// API base URL.
     pub api_base_url: String,
+    /// License key to validate when no saved license is available.
+    pub license_key: Option<String>,
     /// Enable debug logging.
     pub debug: bool,
     /// Enable offline license caching.
     pub enable_offline_cache: bool,
+    /// Where license keys and cached licenses are stored.
+    pub secret_backend: SecretBackend,
+    /// Directory for on-disk state. Defaults to the platform's per-user data
+    /// directory (e.g. `$XDG_DATA_HOME/ironlicensing`).
+    pub storage_dir: Option<PathBuf>,
+    /// Store state in a system-wide directory (e.g. `/var/lib/ironlicensing`)
+    /// shared by all users, for services running as system users.
+    pub system_wide: bool,
     /// Cache validation interval in minutes.
     pub cache_validation_minutes: u32,
     /// Offline grace period in days.
     pub offline_grace_days: u32,
     /// HTTP request timeout.
     pub http_timeout: Duration,
+    /// How long fetched product tiers are served from cache.
+    pub tiers_cache_ttl: Duration,
+    /// Serve expired cached tiers immediately and refresh them in the background.
+    pub stale_while_revalidate: bool,
+    /// Reject license keys whose check character doesn't match before
+    /// contacting the server.
+    pub verify_key_checksum: bool,
+    /// Country hint (ISO 3166-1 alpha-2) for localized tier prices.
+    pub pricing_country: Option<String>,
+    /// Currency hint (ISO 4217) for localized tier prices.
+    pub pricing_currency: Option<String>,
+    /// Treat sandbox licenses as invalid, e.g. in release builds.
+    pub reject_sandbox_licenses: bool,
+    /// Locally declared feature implications, applied on top of the server's.
+    pub feature_hierarchy: FeatureHierarchy,
+    /// Send usage events (trials, tier views, checkouts, activations and
+    /// `track_event` calls) to the server. Off unless the user opts in.
+    pub analytics: bool,
 }
 
 impl LicenseOptions {
@@ -31,12 +138,99 @@ impl LicenseOptions {
         }
     }
 
-    /// Set the API base URL.
+    /// Load options from `IRONLICENSING_*` environment variables.
+    /// See [`LicenseConfig::from_env`].
+    pub fn from_env() -> Result<Self> {
+        LicenseConfig::from_env()?.into_options()
+    }
+
+    /// Load options from a JSON or TOML config file.
+    /// See [`LicenseConfig::from_file`].
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        LicenseConfig::from_file(path)?.into_options()
+    }
+
+    /// Validate the options and normalize them for use by the client.
+    ///
+    /// Trims the API base URL and strips trailing slashes, and rejects
+    /// missing keys, URLs that aren't absolute `http(s)` URLs, product slugs
+    /// that aren't safe as directory names, a zero HTTP timeout, and keys
+    /// whose prefix doesn't match the environment (`pk_test_` against live,
+    /// `pk_live_` against sandbox).
+    /// `LicenseClient::new` calls this, so it only needs to be called directly
+    /// to surface errors early.
+    pub fn build(mut self) -> Result<Self> {
+        self.public_key = self.public_key.trim().to_string();
+        self.product_slug = self.product_slug.trim().to_string();
+
+        if self.public_key.is_empty() {
+            return Err(LicenseError::PublicKeyRequired);
+        }
+        if self.product_slug.is_empty() {
+            return Err(LicenseError::ProductSlugRequired);
+        }
+        if self.product_slug == "."
+            || self.product_slug == ".."
+            || !self
+                .product_slug
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        {
+            return Err(LicenseError::InvalidProductSlug(self.product_slug));
+        }
+
+        self.api_base_url = normalize_base_url(&self.api_base_url)?;
+
+        if self.http_timeout.is_zero() {
+            return Err(LicenseError::InvalidHttpTimeout);
+        }
+
+        // The URL is the source of truth, so assigning the public field
+        // directly also picks the right environment.
+        self.environment = match self.api_base_url.as_str() {
+            LIVE_API_BASE_URL => Environment::Live,
+            SANDBOX_API_BASE_URL => Environment::Sandbox,
+            url => Environment::Custom(url.to_string()),
+        };
+
+        match self.environment {
+            Environment::Live if self.public_key.starts_with(TEST_KEY_PREFIX) => {
+                return Err(LicenseError::KeyEnvironmentMismatch(format!(
+                    "test-mode key '{}...' cannot be used with the production API",
+                    TEST_KEY_PREFIX
+                )));
+            }
+            Environment::Sandbox if self.public_key.starts_with(LIVE_KEY_PREFIX) => {
+                return Err(LicenseError::KeyEnvironmentMismatch(format!(
+                    "live key '{}...' cannot be used with the sandbox API",
+                    LIVE_KEY_PREFIX
+                )));
+            }
+            _ => {}
+        }
+        Ok(self)
+    }
+
+    /// Set the API environment and its base URL.
+    pub fn environment(mut self, environment: Environment) -> Self {
+        self.api_base_url = environment.api_base_url().to_string();
+        self.environment = environment;
+        self
+    }
+
+    /// Set the API base URL. URLs other than the live or sandbox API select
+    /// [`Environment::Custom`].
     pub fn api_base_url(mut self, url: impl Into<String>) -> Self {
         self.api_base_url = url.into();
         self
     }
 
+    /// Set a license key to validate when no saved license is available.
+    pub fn license_key(mut self, key: impl Into<String>) -> Self {
+        self.license_key = Some(key.into());
+        self
+    }
+
     /// Enable or disable debug mode.
     pub fn debug(mut self, debug: bool) -> Self {
         self.debug = debug;
@@ -49,6 +243,24 @@ impl LicenseOptions {
         self
     }
 
+    /// Set where license keys and cached licenses are stored.
+    pub fn secret_backend(mut self, backend: SecretBackend) -> Self {
+        self.secret_backend = backend;
+        self
+    }
+
+    /// Set the directory for on-disk state.
+    pub fn storage_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.storage_dir = Some(dir.into());
+        self
+    }
+
+    /// Enable or disable system-wide storage.
+    pub fn system_wide(mut self, enable: bool) -> Self {
+        self.system_wide = enable;
+        self
+    }
+
     /// Set cache validation interval in minutes.
     pub fn cache_validation_minutes(mut self, minutes: u32) -> Self {
         self.cache_validation_minutes = minutes;
@@ -66,6 +278,54 @@ impl LicenseOptions {
         self.http_timeout = timeout;
         self
     }
+
+    /// Set how long fetched product tiers are served from cache.
+    pub fn tiers_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.tiers_cache_ttl = ttl;
+        self
+    }
+
+    /// Enable or disable stale-while-revalidate for product tiers.
+    pub fn stale_while_revalidate(mut self, enable: bool) -> Self {
+        self.stale_while_revalidate = enable;
+        self
+    }
+
+    /// Enable or disable local license key checksum verification.
+    pub fn verify_key_checksum(mut self, enable: bool) -> Self {
+        self.verify_key_checksum = enable;
+        self
+    }
+
+    /// Request tier prices localized for a country.
+    pub fn pricing_country(mut self, country: impl Into<String>) -> Self {
+        self.pricing_country = Some(country.into());
+        self
+    }
+
+    /// Request tier prices in a currency.
+    pub fn pricing_currency(mut self, currency: impl Into<String>) -> Self {
+        self.pricing_currency = Some(currency.into());
+        self
+    }
+
+    /// Treat sandbox licenses as invalid.
+    pub fn reject_sandbox_licenses(mut self, reject: bool) -> Self {
+        self.reject_sandbox_licenses = reject;
+        self
+    }
+
+    /// Set locally declared feature implications.
+    pub fn feature_hierarchy(mut self, hierarchy: FeatureHierarchy) -> Self {
+        self.feature_hierarchy = hierarchy;
+        self
+    }
+
+    /// Enable usage analytics.
+    pub fn analytics(mut self, enable: bool) -> Self {
+        self.analytics = enable;
+        self
+    }
 }
 
 impl Default for LicenseOptions {
@@ -73,12 +333,369 @@ impl Default for LicenseOptions {
         Self {
             public_key: String::new(),
             product_slug: String::new(),
-            api_base_url: "https://api.ironlicensing.com".to_string(),
+            environment: Environment::Live,
+            api_base_url: LIVE_API_BASE_URL.to_string(),
+            license_key: None,
             debug: false,
             enable_offline_cache: true,
+            secret_backend: SecretBackend::default(),
+            storage_dir: None,
+            system_wide: false,
             cache_validation_minutes: 60,
             offline_grace_days: 7,
             http_timeout: Duration::from_secs(30),
+            tiers_cache_ttl: Duration::from_secs(60 * 60),
+            stale_while_revalidate: false,
+            verify_key_checksum: false,
+            pricing_country: None,
+            pricing_currency: None,
+            reject_sandbox_licenses: false,
+            feature_hierarchy: FeatureHierarchy::default(),
+            analytics: false,
         }
     }
 }
+
+/// Serializable form of [`LicenseOptions`] for config files and the
+/// environment. Unset fields keep their defaults.
+///
+/// ```toml
+/// public_key = "pk_live_xxx"
+/// product_slug = "your-product"
+/// http_timeout_secs = 10
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LicenseConfig {
+    pub public_key: Option<String>,
+    pub product_slug: Option<String>,
+    /// `"live"` or `"sandbox"`. Overridden by `api_base_url`.
+    pub environment: Option<String>,
+    pub api_base_url: Option<String>,
+    pub license_key: Option<String>,
+    pub debug: Option<bool>,
+    pub enable_offline_cache: Option<bool>,
+    /// `"encrypted_file"` or `"secret_service"`.
+    pub secret_backend: Option<String>,
+    pub storage_dir: Option<PathBuf>,
+    pub system_wide: Option<bool>,
+    pub cache_validation_minutes: Option<u32>,
+    pub offline_grace_days: Option<u32>,
+    pub http_timeout_secs: Option<u64>,
+    pub tiers_cache_ttl_secs: Option<u64>,
+    pub stale_while_revalidate: Option<bool>,
+    pub verify_key_checksum: Option<bool>,
+    pub pricing_country: Option<String>,
+    pub pricing_currency: Option<String>,
+    pub reject_sandbox_licenses: Option<bool>,
+    /// Feature keys mapped to the features they imply. Not read from the environment.
+    pub implied_features: Option<BTreeMap<String, Vec<String>>>,
+    pub analytics: Option<bool>,
+}
+
+impl LicenseConfig {
+    /// Read configuration from environment variables named after the
+    /// fields with an `IRONLICENSING_` prefix, e.g. `IRONLICENSING_PUBLIC_KEY`,
+    /// `IRONLICENSING_PRODUCT_SLUG`, `IRONLICENSING_API_BASE_URL` and
+    /// `IRONLICENSING_LICENSE_KEY`.
+    pub fn from_env() -> Result<Self> {
+        Ok(Self {
+            public_key: env_var("PUBLIC_KEY"),
+            product_slug: env_var("PRODUCT_SLUG"),
+            environment: env_var("ENVIRONMENT"),
+            api_base_url: env_var("API_BASE_URL"),
+            license_key: env_var("LICENSE_KEY"),
+            debug: env_bool("DEBUG")?,
+            enable_offline_cache: env_bool("ENABLE_OFFLINE_CACHE")?,
+            secret_backend: env_var("SECRET_BACKEND"),
+            storage_dir: env_var("STORAGE_DIR").map(PathBuf::from),
+            system_wide: env_bool("SYSTEM_WIDE")?,
+            cache_validation_minutes: env_parse("CACHE_VALIDATION_MINUTES")?,
+            offline_grace_days: env_parse("OFFLINE_GRACE_DAYS")?,
+            http_timeout_secs: env_parse("HTTP_TIMEOUT_SECS")?,
+            tiers_cache_ttl_secs: env_parse("TIERS_CACHE_TTL_SECS")?,
+            stale_while_revalidate: env_bool("STALE_WHILE_REVALIDATE")?,
+            verify_key_checksum: env_bool("VERIFY_KEY_CHECKSUM")?,
+            pricing_country: env_var("PRICING_COUNTRY"),
+            pricing_currency: env_var("PRICING_CURRENCY"),
+            reject_sandbox_licenses: env_bool("REJECT_SANDBOX_LICENSES")?,
+            implied_features: None,
+            analytics: env_bool("ANALYTICS")?,
+        })
+    }
+
+    /// Parse configuration from JSON. A bad value is reported as
+    /// [`LicenseError::InvalidConfig`] naming its field.
+    pub fn from_json_str(json: &str) -> Result<Self> {
+        let mut deserializer = serde_json::Deserializer::from_str(json);
+        let config = serde_path_to_error::deserialize(&mut deserializer).map_err(|e| {
+            let message = e.inner().to_string();
+            field_error(e.path(), message).unwrap_or_else(|| e.into_inner().into())
+        })?;
+        deserializer.end()?;
+        Ok(config)
+    }
+
+    /// Parse configuration from TOML. A bad value is reported as
+    /// [`LicenseError::InvalidConfig`] naming its field.
+    #[cfg(feature = "toml")]
+    pub fn from_toml_str(toml: &str) -> Result<Self> {
+        serde_path_to_error::deserialize(toml::Deserializer::new(toml)).map_err(|e| {
+            let message = e.inner().message().to_string();
+            field_error(e.path(), message).unwrap_or_else(|| e.into_inner().into())
+        })
+    }
+
+    /// Read configuration from a file, parsed as TOML if the extension is
+    /// `.toml` and as JSON otherwise.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)?;
+
+        match path.extension().and_then(|e| e.to_str()) {
+            #[cfg(feature = "toml")]
+            Some("toml") => Self::from_toml_str(&contents),
+            #[cfg(not(feature = "toml"))]
+            Some("toml") => Err(LicenseError::InvalidConfig {
+                field: path.display().to_string(),
+                message: "TOML config files require the `toml` feature".to_string(),
+            }),
+            _ => Self::from_json_str(&contents),
+        }
+    }
+
+    /// Combine two configurations, preferring values set in `overrides`.
+    /// Useful for layering environment variables over a config file.
+    pub fn merge(self, overrides: Self) -> Self {
+        Self {
+            public_key: overrides.public_key.or(self.public_key),
+            product_slug: overrides.product_slug.or(self.product_slug),
+            environment: overrides.environment.or(self.environment),
+            api_base_url: overrides.api_base_url.or(self.api_base_url),
+            license_key: overrides.license_key.or(self.license_key),
+            debug: overrides.debug.or(self.debug),
+            enable_offline_cache: overrides.enable_offline_cache.or(self.enable_offline_cache),
+            secret_backend: overrides.secret_backend.or(self.secret_backend),
+            storage_dir: overrides.storage_dir.or(self.storage_dir),
+            system_wide: overrides.system_wide.or(self.system_wide),
+            cache_validation_minutes: overrides.cache_validation_minutes.or(self.cache_validation_minutes),
+            offline_grace_days: overrides.offline_grace_days.or(self.offline_grace_days),
+            http_timeout_secs: overrides.http_timeout_secs.or(self.http_timeout_secs),
+            tiers_cache_ttl_secs: overrides.tiers_cache_ttl_secs.or(self.tiers_cache_ttl_secs),
+            stale_while_revalidate: overrides.stale_while_revalidate.or(self.stale_while_revalidate),
+            verify_key_checksum: overrides.verify_key_checksum.or(self.verify_key_checksum),
+            pricing_country: overrides.pricing_country.or(self.pricing_country),
+            pricing_currency: overrides.pricing_currency.or(self.pricing_currency),
+            reject_sandbox_licenses: overrides.reject_sandbox_licenses.or(self.reject_sandbox_licenses),
+            implied_features: overrides.implied_features.or(self.implied_features),
+            analytics: overrides.analytics.or(self.analytics),
+        }
+    }
+
+    /// Convert to [`LicenseOptions`], checking that required fields are set
+    /// and validating the result with [`LicenseOptions::build`].
+    pub fn into_options(self) -> Result<LicenseOptions> {
+        let public_key = required("public_key", self.public_key)?;
+        let product_slug = required("product_slug", self.product_slug)?;
+        let mut options = LicenseOptions::new(public_key, product_slug);
+
+        if let Some(environment) = self.environment {
+            let environment = Environment::parse(&environment).ok_or_else(|| LicenseError::InvalidConfig {
+                field: "environment".to_string(),
+                message: format!("expected 'live' or 'sandbox', got '{}'", environment),
+            })?;
+            options = options.environment(environment);
+        }
+        if let Some(url) = self.api_base_url {
+            options.api_base_url = url;
+        }
+        if let Some(backend) = self.secret_backend {
+            options.secret_backend = parse_secret_backend(&backend)?;
+        }
+        if let Some(secs) = self.http_timeout_secs {
+            options.http_timeout = Duration::from_secs(secs);
+        }
+        if let Some(secs) = self.tiers_cache_ttl_secs {
+            options.tiers_cache_ttl = Duration::from_secs(secs);
+        }
+
+        options.license_key = self.license_key.or(options.license_key);
+        options.debug = self.debug.unwrap_or(options.debug);
+        options.enable_offline_cache = self.enable_offline_cache.unwrap_or(options.enable_offline_cache);
+        options.storage_dir = self.storage_dir.or(options.storage_dir);
+        options.system_wide = self.system_wide.unwrap_or(options.system_wide);
+        options.cache_validation_minutes = self.cache_validation_minutes.unwrap_or(options.cache_validation_minutes);
+        options.offline_grace_days = self.offline_grace_days.unwrap_or(options.offline_grace_days);
+        options.stale_while_revalidate = self.stale_while_revalidate.unwrap_or(options.stale_while_revalidate);
+        options.verify_key_checksum = self.verify_key_checksum.unwrap_or(options.verify_key_checksum);
+        options.pricing_country = self.pricing_country.or(options.pricing_country);
+        options.pricing_currency = self.pricing_currency.or(options.pricing_currency);
+        options.reject_sandbox_licenses = self.reject_sandbox_licenses.unwrap_or(options.reject_sandbox_licenses);
+        options.analytics = self.analytics.unwrap_or(options.analytics);
+        for (feature, implied) in self.implied_features.unwrap_or_default() {
+            options.feature_hierarchy = options.feature_hierarchy.implies(feature, implied);
+        }
+
+        options.build()
+    }
+}
+
+/// Check that `url` is an absolute `http(s)` URL without query or fragment,
+/// and strip trailing slashes so paths can be appended directly.
+fn normalize_base_url(url: &str) -> Result<String> {
+    let trimmed = url.trim().trim_end_matches('/');
+    let invalid = |reason: &str| LicenseError::InvalidApiBaseUrl(format!("'{}' {}", url, reason));
+
+    let parsed = reqwest::Url::parse(trimmed).map_err(|e| invalid(&format!("is not a valid URL ({})", e)))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(invalid("must use http or https"));
+    }
+    if parsed.host_str().is_none_or(str::is_empty) {
+        return Err(invalid("has no host"));
+    }
+    if parsed.query().is_some() || parsed.fragment().is_some() {
+        return Err(invalid("must not contain a query or fragment"));
+    }
+
+    Ok(trimmed.to_string())
+}
+
+fn required(field: &str, value: Option<String>) -> Result<String> {
+    value.filter(|v| !v.is_empty()).ok_or_else(|| LicenseError::InvalidConfig {
+        field: field.to_string(),
+        message: "is required".to_string(),
+    })
+}
+
+/// An error for a config file value at `path`, or `None` if the error isn't
+/// inside any field, e.g. malformed syntax at the top level.
+fn field_error(path: &serde_path_to_error::Path, message: String) -> Option<LicenseError> {
+    if path
+        .iter()
+        .all(|segment| matches!(segment, serde_path_to_error::Segment::Unknown))
+    {
+        return None;
+    }
+    Some(LicenseError::InvalidConfig {
+        field: path.to_string(),
+        message,
+    })
+}
+
+fn parse_secret_backend(value: &str) -> Result<SecretBackend> {
+    match value {
+        "encrypted_file" => Ok(SecretBackend::EncryptedFile),
+        #[cfg(all(target_os = "linux", feature = "secret-service"))]
+        "secret_service" => Ok(SecretBackend::SecretService),
+        other => Err(LicenseError::InvalidConfig {
+            field: "secret_backend".to_string(),
+            message: format!("unsupported backend '{}'", other),
+        }),
+    }
+}
+
+fn env_var(name: &str) -> Option<String> {
+    std::env::var(format!("{}{}", ENV_PREFIX, name))
+        .ok()
+        .filter(|v| !v.is_empty())
+}
+
+fn env_parse<T: FromStr>(name: &str) -> Result<Option<T>>
+where
+    T::Err: std::fmt::Display,
+{
+    env_var(name)
+        .map(|v| {
+            v.trim().parse().map_err(|e: T::Err| LicenseError::InvalidConfig {
+                field: format!("{}{}", ENV_PREFIX, name),
+                message: e.to_string(),
+            })
+        })
+        .transpose()
+}
+
+fn env_bool(name: &str) -> Result<Option<bool>> {
+    env_var(name)
+        .map(|v| match v.trim().to_lowercase().as_str() {
+            "1" | "true" | "yes" | "on" => Ok(true),
+            "0" | "false" | "no" | "off" => Ok(false),
+            _ => Err(LicenseError::InvalidConfig {
+                field: format!("{}{}", ENV_PREFIX, name),
+                message: format!("expected a boolean, got '{}'", v),
+            }),
+        })
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(public_key: &str) -> LicenseOptions {
+        LicenseOptions::new(public_key, "my-product")
+    }
+
+    #[test]
+    fn build_strips_trailing_slashes_and_picks_environment_from_url() {
+        let built = options("pk_live_x").api_base_url(format!("{}/", LIVE_API_BASE_URL)).build().unwrap();
+        assert_eq!(built.api_base_url, LIVE_API_BASE_URL);
+        assert_eq!(built.environment, Environment::Live);
+
+        let mut sandbox = options("pk_test_x");
+        sandbox.api_base_url = format!("{}//", SANDBOX_API_BASE_URL);
+        assert_eq!(sandbox.build().unwrap().environment, Environment::Sandbox);
+
+        let custom = options("pk_live_x").api_base_url(" http://localhost:8080/ ").build().unwrap();
+        assert_eq!(custom.api_base_url, "http://localhost:8080");
+        assert_eq!(custom.environment, Environment::Custom("http://localhost:8080".to_string()));
+    }
+
+    #[test]
+    fn build_rejects_malformed_urls() {
+        for url in ["not a url", "ftp://example.com", "https://example.com/api?v=1", "https://example.com/#top"] {
+            let result = options("pk_live_x").api_base_url(url).build();
+            assert!(matches!(result, Err(LicenseError::InvalidApiBaseUrl(_))), "{}", url);
+        }
+    }
+
+    #[test]
+    fn build_rejects_keys_for_the_wrong_environment() {
+        let result = options("pk_test_x").build();
+        assert!(matches!(result, Err(LicenseError::KeyEnvironmentMismatch(_))));
+
+        let result = options("pk_live_x").environment(Environment::Sandbox).build();
+        assert!(matches!(result, Err(LicenseError::KeyEnvironmentMismatch(_))));
+
+        assert!(options("pk_test_x").environment(Environment::Sandbox).build().is_ok());
+        assert!(options("pk_test_x").api_base_url("http://localhost:8080").build().is_ok());
+        assert!(options("pk_live_x").api_base_url("http://localhost:8080").build().is_ok());
+    }
+
+    #[test]
+    fn build_rejects_zero_timeout() {
+        let result = options("pk_live_x").http_timeout(Duration::ZERO).build();
+        assert!(matches!(result, Err(LicenseError::InvalidHttpTimeout)));
+        assert!(options("pk_live_x").http_timeout(Duration::from_millis(1)).build().is_ok());
+    }
+
+    #[test]
+    fn build_requires_key_and_slug() {
+        let result = LicenseOptions::new("  ", "my-product").build();
+        assert!(matches!(result, Err(LicenseError::PublicKeyRequired)));
+
+        let result = LicenseOptions::new("pk_live_x", " ").build();
+        assert!(matches!(result, Err(LicenseError::ProductSlugRequired)));
+    }
+
+    #[test]
+    fn build_rejects_slugs_unsafe_as_directory_names() {
+        for slug in [".", "..", "../etc", "a/b", r"a\b", "my product", "prodüct"] {
+            let result = LicenseOptions::new("pk_live_x", slug).build();
+            assert!(matches!(result, Err(LicenseError::InvalidProductSlug(_))), "{}", slug);
+        }
+
+        let built = LicenseOptions::new(" pk_live_x ", " My-Product_2.0 ").build().unwrap();
+        assert_eq!(built.public_key, "pk_live_x");
+        assert_eq!(built.product_slug, "My-Product_2.0");
+    }
+}
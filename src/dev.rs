@@ -0,0 +1,54 @@
+use crate::error::{LicenseError, Result};
+use crate::types::License;
+use serde_json::{json, Value};
+use std::fs;
+use std::path::Path;
+
+/// Environment variable holding a dev license as inline JSON or a path to a JSON file.
+const DEV_LICENSE_ENV: &str = "IRONLICENSING_DEV_LICENSE";
+
+/// File in the working directory read when the environment variable is unset.
+const DEV_LICENSE_FILE: &str = "ironlicensing.dev.json";
+
+/// Load the development license override, if one is configured.
+///
+/// Only compiled into debug builds with the `dev-override` feature; release
+/// builds never consult the environment or the file, even with the feature on.
+pub(crate) fn load() -> Result<Option<License>> {
+    let json = match std::env::var(DEV_LICENSE_ENV) {
+        Ok(value) if value.trim_start().starts_with('{') => value,
+        Ok(path) if !path.trim().is_empty() => fs::read_to_string(path.trim())?,
+        _ if Path::new(DEV_LICENSE_FILE).is_file() => fs::read_to_string(DEV_LICENSE_FILE)?,
+        _ => return Ok(None),
+    };
+    parse(&json).map(Some)
+}
+
+/// Parse a dev license. Fields the server would always send default to an
+/// valid perpetual license, and features may be given as plain keys.
+fn parse(json: &str) -> Result<License> {
+    let mut value: Value = serde_json::from_str(json)?;
+    let fields = value.as_object_mut().ok_or_else(|| LicenseError::InvalidConfig {
+        field: DEV_LICENSE_ENV.to_string(),
+        message: "expected a JSON object describing a license".to_string(),
+    })?;
+
+    for (field, default) in [
+        ("id", json!("dev")),
+        ("key", json!("DEV-LICENSE")),
+        ("status", json!("valid")),
+        ("type", json!("perpetual")),
+    ] {
+        fields.entry(field).or_insert(default);
+    }
+
+    if let Some(Value::Array(features)) = fields.get_mut("features") {
+        for feature in features.iter_mut() {
+            if let Value::String(key) = feature {
+                *feature = json!({ "key": key, "name": key, "enabled": true });
+            }
+        }
+    }
+
+    Ok(serde_json::from_value(value)?)
+}
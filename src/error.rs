@@ -15,10 +15,50 @@ pub enum LicenseError {
     #[error("Product slug is required")]
     ProductSlugRequired,
 
+    /// Product slug contains characters that aren't safe in a directory name.
+    #[error("Invalid product slug '{0}': use letters, digits, '-', '_' or '.'")]
+    InvalidProductSlug(String),
+
+    /// API base URL is malformed.
+    #[error("Invalid API base URL: {0}")]
+    InvalidApiBaseUrl(String),
+
+    /// HTTP timeout is zero.
+    #[error("HTTP timeout must be greater than zero")]
+    InvalidHttpTimeout,
+
+    /// Public key doesn't match the API environment.
+    #[error("Public key does not match the API environment: {0}")]
+    KeyEnvironmentMismatch(String),
+
+    /// No license is loaded in the client.
+    #[error("No license is loaded")]
+    NoLicense,
+
+    /// The license key is malformed.
+    #[error("Invalid license key: {0}")]
+    InvalidLicenseKey(String),
+
+    /// A build date or version couldn't be parsed.
+    #[error("Invalid build version '{0}': expected a date like 2026-03-01 or a version like 2.4.1")]
+    InvalidBuildVersion(String),
+
+    /// A configuration value is missing or invalid.
+    #[error("Invalid configuration for '{field}': {message}")]
+    InvalidConfig { field: String, message: String },
+
     /// A required feature is not available.
     #[error("Feature '{0}' requires a valid license")]
     FeatureRequired(String),
 
+    /// A checkout session ID can't be used in a URL.
+    #[error("Invalid checkout session ID '{0}'")]
+    InvalidSessionId(String),
+
+    /// Timed out waiting for a checkout session to finish.
+    #[error("Timed out waiting for checkout session '{0}'")]
+    CheckoutTimeout(String),
+
     /// HTTP request error.
     #[error("HTTP error: {0}")]
     Http(#[from] reqwest::Error),
@@ -27,13 +67,26 @@ pub enum LicenseError {
     #[error("JSON error: {0}")]
     Json(#[from] serde_json::Error),
 
+    /// TOML deserialization error.
+    #[cfg(feature = "toml")]
+    #[error("TOML error: {0}")]
+    Toml(#[from] toml::de::Error),
+
     /// IO error.
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 
+    /// Secret store error.
+    #[error("Secret store error: {0}")]
+    SecretStore(String),
+
     /// API error returned from the server.
     #[error("API error: {0}")]
     Api(String),
+
+    /// The server failed to handle the request (a 5xx status); retrying may succeed.
+    #[error("Server error ({status}): {message}")]
+    Server { status: u16, message: String },
 }
 
 pub type Result<T> = std::result::Result<T, LicenseError>;
@@ -0,0 +1,88 @@
+use crate::types::{License, LicenseStatus};
+use parking_lot::Mutex;
+use std::sync::mpsc::{self, Receiver, Sender};
+
+/// Capacity of the async broadcast channel before slow receivers lag.
+#[cfg(feature = "tokio")]
+const ASYNC_CHANNEL_CAPACITY: usize = 64;
+
+/// A change to the client's license state.
+#[derive(Debug, Clone)]
+pub enum LicenseEvent {
+    /// A license key was validated.
+    Validated(License),
+    /// A license key was activated on this machine.
+    Activated(License),
+    /// A trial was started.
+    TrialStarted(License),
+    /// The trial was extended.
+    TrialExtended(License),
+    /// The trial was converted to a full license.
+    TrialConverted(License),
+    /// The license was deactivated from this machine.
+    Deactivated,
+    /// The license status changed.
+    StatusChanged {
+        from: LicenseStatus,
+        to: LicenseStatus,
+    },
+    /// The set of enabled features changed.
+    FeaturesChanged {
+        added: Vec<String>,
+        removed: Vec<String>,
+    },
+}
+
+/// Fan-out of license events to sync and async subscribers.
+pub(crate) struct EventBus {
+    senders: Mutex<Vec<Sender<LicenseEvent>>>,
+    #[cfg(feature = "tokio")]
+    broadcast: tokio::sync::broadcast::Sender<LicenseEvent>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self {
+            senders: Mutex::new(Vec::new()),
+            #[cfg(feature = "tokio")]
+            broadcast: tokio::sync::broadcast::channel(ASYNC_CHANNEL_CAPACITY).0,
+        }
+    }
+
+    pub fn subscribe(&self) -> Receiver<LicenseEvent> {
+        let (tx, rx) = mpsc::channel();
+        self.senders.lock().push(tx);
+        rx
+    }
+
+    #[cfg(feature = "tokio")]
+    pub fn subscribe_async(&self) -> tokio::sync::broadcast::Receiver<LicenseEvent> {
+        self.broadcast.subscribe()
+    }
+
+    pub fn emit(&self, event: LicenseEvent) {
+        #[cfg(feature = "tokio")]
+        let _ = self.broadcast.send(event.clone());
+
+        // Drop senders whose receiver has gone away.
+        self.senders.lock().retain(|tx| tx.send(event.clone()).is_ok());
+    }
+
+    /// Emit a `StatusChanged` event if the status differs between two license states.
+    pub fn emit_status_change(&self, previous: Option<&License>, current: Option<&License>) {
+        let status = |l: Option<&License>| l.map(|l| l.status).unwrap_or_default();
+        let (from, to) = (status(previous), status(current));
+        if from != to {
+            self.emit(LicenseEvent::StatusChanged { from, to });
+        }
+    }
+
+    /// Emit a `FeaturesChanged` event if the enabled feature keys differ.
+    pub fn emit_feature_changes(&self, before: &[String], after: &[String]) {
+        let added: Vec<String> = after.iter().filter(|k| !before.contains(k)).cloned().collect();
+        let removed: Vec<String> = before.iter().filter(|k| !after.contains(k)).cloned().collect();
+        if !added.is_empty() || !removed.is_empty() {
+            self.emit(LicenseEvent::FeaturesChanged { added, removed });
+        }
+    }
+}
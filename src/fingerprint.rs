@@ -0,0 +1,201 @@
+use crate::transport::Transport;
+use sha2::{Digest, Sha256};
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+use std::fs;
+
+/// Files holding the OS-assigned machine ID on Linux and other Unixes.
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+const OS_MACHINE_ID_PATHS: &[&str] = &["/etc/machine-id", "/var/lib/dbus/machine-id"];
+
+/// DMI files describing the hardware model on Linux, readable without root.
+#[cfg(target_os = "linux")]
+const DMI_MODEL_PATHS: &[&str] = &[
+    "/sys/class/dmi/id/sys_vendor",
+    "/sys/class/dmi/id/product_name",
+    "/sys/class/dmi/id/board_vendor",
+    "/sys/class/dmi/id/board_name",
+];
+
+/// DMI files identifying this particular unit on Linux. Usually readable
+/// only by root.
+#[cfg(target_os = "linux")]
+const DMI_UNIT_PATHS: &[&str] = &["/sys/class/dmi/id/product_uuid", "/sys/class/dmi/id/board_serial"];
+
+/// A stable hash of the OS-assigned machine ID, or `None` if the OS doesn't
+/// provide one.
+///
+/// Unlike the SDK's machine ID, which is a random UUID persisted on disk,
+/// the fingerprint is recomputed from the environment and survives the
+/// SDK's storage directory being deleted. It never falls back to the
+/// hostname, which is guessable and changes with the network on some systems.
+pub(crate) fn machine_fingerprint() -> Option<String> {
+    let id = os_machine_id()?;
+    let mut hasher = Sha256::new();
+    hasher.update(id.as_bytes());
+    hasher.update([0]);
+    hasher.update(Transport::get_platform().as_bytes());
+
+    Some(hex(&hasher.finalize()))
+}
+
+/// A hash of hardware signals, or `None` if none identify this particular
+/// unit.
+///
+/// It is sent with activations to recognize this machine after its machine
+/// ID changes. The hardware model alone is shared by every machine of that
+/// model, so without a per-unit signal (a hardware UUID or serial, a built-in
+/// NIC's MAC address, or failing those the OS machine ID) there is no fingerprint
+/// and activations are never transferred automatically.
+pub(crate) fn hardware_fingerprint() -> Option<String> {
+    let signals = hardware_signals();
+    if !signals.iter().any(|s| s.per_unit) {
+        return None;
+    }
+
+    let mut hasher = Sha256::new();
+    for signal in signals {
+        hasher.update(signal.value.as_bytes());
+        hasher.update([0]);
+    }
+    hasher.update(Transport::get_platform().as_bytes());
+
+    Some(hex(&hasher.finalize()))
+}
+
+/// A value read from the hardware or OS.
+struct Signal {
+    value: String,
+    /// Whether the value identifies this unit rather than its model.
+    per_unit: bool,
+}
+
+impl Signal {
+    fn model(value: String) -> Self {
+        Self { value, per_unit: false }
+    }
+
+    fn unit(value: String) -> Self {
+        Self { value, per_unit: true }
+    }
+}
+
+/// Hardware model plus the hardware UUID and serial. Failing those, the MAC
+/// addresses of built-in network interfaces, and failing those the OS
+/// machine ID.
+///
+/// MACs are a last resort because the set of interfaces changes: plugging
+/// in a USB adapter or a dock must not change the fingerprint, so only
+/// interfaces that aren't on a USB bus or a removable device count.
+#[cfg(target_os = "linux")]
+fn hardware_signals() -> Vec<Signal> {
+    let read = |path: std::path::PathBuf| {
+        fs::read_to_string(path)
+            .ok()
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+    };
+
+    let mut signals: Vec<Signal> = DMI_MODEL_PATHS
+        .iter()
+        .filter_map(|p| read(p.into()))
+        .map(Signal::model)
+        .collect();
+    signals.extend(DMI_UNIT_PATHS.iter().filter_map(|p| read(p.into())).map(Signal::unit));
+
+    if !signals.iter().any(|s| s.per_unit) {
+        let mut macs: Vec<String> = fs::read_dir("/sys/class/net")
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter(|entry| is_builtin_interface(&entry.path()))
+            .filter_map(|entry| read(entry.path().join("address")))
+            .filter(|mac| mac != "00:00:00:00:00:00")
+            .collect();
+        macs.sort();
+        signals.extend(macs.into_iter().map(Signal::unit));
+    }
+
+    if !signals.iter().any(|s| s.per_unit) {
+        signals.extend(os_machine_id().map(Signal::unit));
+    }
+    signals
+}
+
+/// Whether a network interface in `/sys/class/net` is part of the machine:
+/// backed by a device (virtual interfaces have none) with no USB bus or
+/// removable device, such as a Thunderbolt dock, above it.
+#[cfg(target_os = "linux")]
+fn is_builtin_interface(interface: &std::path::Path) -> bool {
+    let Ok(device) = fs::canonicalize(interface.join("device")) else {
+        return false;
+    };
+    device
+        .ancestors()
+        .take_while(|dir| dir.starts_with("/sys/devices"))
+        .all(|dir| {
+            let usb = dir
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with("usb"));
+            let removable = fs::read_to_string(dir.join("removable")).is_ok_and(|s| s.trim() == "removable");
+            !usb && !removable
+        })
+}
+
+/// The logic board's platform UUID.
+#[cfg(target_os = "macos")]
+fn hardware_signals() -> Vec<Signal> {
+    os_machine_id().map(Signal::unit).into_iter().collect()
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn hardware_signals() -> Vec<Signal> {
+    Vec::new()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn os_machine_id() -> Option<String> {
+    OS_MACHINE_ID_PATHS
+        .iter()
+        .filter_map(|p| fs::read_to_string(p).ok())
+        .map(|id| id.trim().to_string())
+        .find(|id| !id.is_empty())
+}
+
+/// The `IOPlatformUUID` of the logic board.
+#[cfg(target_os = "macos")]
+fn os_machine_id() -> Option<String> {
+    command_output("ioreg", &["-rd1", "-c", "IOPlatformExpertDevice"])?
+        .lines()
+        .find(|line| line.contains("\"IOPlatformUUID\""))
+        .and_then(|line| line.split('"').nth(3))
+        .map(str::to_string)
+        .filter(|id| !id.is_empty())
+}
+
+/// The `MachineGuid` Windows assigns at install time.
+#[cfg(target_os = "windows")]
+fn os_machine_id() -> Option<String> {
+    command_output(
+        "reg",
+        &["query", r"HKLM\SOFTWARE\Microsoft\Cryptography", "/v", "MachineGuid"],
+    )?
+    .lines()
+    .find(|line| line.trim_start().starts_with("MachineGuid"))
+    .and_then(|line| line.split_whitespace().last())
+    .map(str::to_string)
+    .filter(|id| !id.is_empty())
+}
+
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    std::process::Command::new(program)
+        .args(args)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+}
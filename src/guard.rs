@@ -0,0 +1,79 @@
+use crate::client::LicenseClient;
+use crate::error::{LicenseError, Result};
+use crate::types::Feature;
+use std::ops::Deref;
+
+/// Proof that a feature was available when the guard was acquired.
+///
+/// The guard holds a snapshot of the feature and derefs to it, so gated code
+/// can read feature metadata for as long as the guard is in scope.
+#[derive(Debug)]
+pub struct FeatureGuard {
+    feature: Feature,
+}
+
+impl FeatureGuard {
+    /// Acquire a guard for a feature using the global client.
+    pub fn acquire(feature_key: impl AsRef<str>) -> Result<Self> {
+        Self::acquire_with(crate::get_client()?, feature_key)
+    }
+
+    /// Acquire a guard for a feature using the given client.
+    pub fn acquire_with(client: &LicenseClient, feature_key: impl AsRef<str>) -> Result<Self> {
+        let feature_key = feature_key.as_ref();
+        match client.get_feature(feature_key) {
+            Some(feature) if client.has_feature(feature_key) => Ok(Self { feature }),
+            _ => Err(LicenseError::FeatureRequired(feature_key.to_string())),
+        }
+    }
+
+    /// Acquire a guard using the global client, or run `fallback` if the
+    /// feature is not available.
+    pub fn acquire_or_else<E, F>(feature_key: impl AsRef<str>, fallback: F) -> std::result::Result<Self, E>
+    where
+        F: FnOnce(LicenseError) -> E,
+    {
+        Self::acquire(feature_key).map_err(fallback)
+    }
+
+    /// Get the feature this guard was acquired for.
+    pub fn feature(&self) -> &Feature {
+        &self.feature
+    }
+}
+
+impl Deref for FeatureGuard {
+    type Target = Feature;
+
+    fn deref(&self) -> &Feature {
+        &self.feature
+    }
+}
+
+/// Run a block only if a feature is available in the global client.
+///
+/// Without an `else` branch the macro evaluates to
+/// `Result<T, LicenseError>`. With an `else` branch the fallback block runs
+/// when the feature is missing or the global client is not initialized.
+///
+/// ```rust,no_run
+/// use ironlicensing::feature_gate;
+///
+/// let rows = feature_gate!("premium", { 500 } else { 50 });
+///
+/// let exported = feature_gate!("export-pdf", {
+///     println!("Exporting...");
+/// });
+/// ```
+#[macro_export]
+macro_rules! feature_gate {
+    ($feature:expr, $body:block else $fallback:block) => {
+        if $crate::has_feature($feature).unwrap_or(false) $body else $fallback
+    };
+    ($feature:expr, $body:block) => {
+        match $crate::require_feature($feature) {
+            ::std::result::Result::Ok(()) => ::std::result::Result::Ok($body),
+            ::std::result::Result::Err(e) => ::std::result::Result::<_, $crate::LicenseError>::Err(e),
+        }
+    };
+}
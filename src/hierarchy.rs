@@ -0,0 +1,204 @@
+use crate::types::Feature;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Rules for features that enable other features, e.g. `enterprise`
+/// implying `premium` and `sso`.
+///
+/// Servers can send implications with each feature (`Feature::implies`);
+/// rules declared here are applied on top of those. Implications are
+/// transitive and cycles are harmless.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FeatureHierarchy {
+    implies: HashMap<String, Vec<String>>,
+}
+
+impl FeatureHierarchy {
+    /// Create an empty hierarchy.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declare that `feature` implies each of `implied`.
+    pub fn implies<I>(mut self, feature: impl AsRef<str>, implied: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        self.implies
+            .entry(feature.as_ref().to_string())
+            .or_default()
+            .extend(implied.into_iter().map(|f| f.as_ref().to_string()));
+        self
+    }
+
+    /// Features directly implied by `feature` under these rules.
+    pub fn implied_by(&self, feature: &str) -> &[String] {
+        self.implies.get(feature).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Expand the active features in `features` (enabled and inside their
+    /// validity window) with everything they imply.
+    ///
+    /// Implied features keep their details if they appear in `features`
+    /// (even when inactive there) and are otherwise named after their key.
+    pub fn expand(&self, features: &[Feature]) -> Vec<Feature> {
+        let mut expanded: Vec<Feature> = Vec::new();
+        let mut seen: HashSet<String> = HashSet::new();
+        let mut queue: VecDeque<Feature> = features.iter().filter(|f| f.is_active()).cloned().collect();
+
+        while let Some(feature) = queue.pop_front() {
+            if !seen.insert(feature.key.clone()) {
+                continue;
+            }
+
+            for key in feature.implies.iter().chain(self.implied_by(&feature.key)) {
+                if seen.contains(key) {
+                    continue;
+                }
+                let implied = match features.iter().find(|f| &f.key == key) {
+                    // Granted through the implying feature, so its own window doesn't apply.
+                    Some(f) => Feature {
+                        enabled: true,
+                        starts_at: None,
+                        expires_at: None,
+                        source: feature.source.clone(),
+                        ..f.clone()
+                    },
+                    None => Feature {
+                        source: feature.source.clone(),
+                        ..Feature::named(key)
+                    },
+                };
+                queue.push_back(implied);
+            }
+            expanded.push(feature);
+        }
+
+        expanded
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::FeatureSource;
+    use chrono::{Duration, Utc};
+
+    fn keys(features: &[Feature]) -> Vec<&str> {
+        let mut keys: Vec<&str> = features.iter().map(|f| f.key.as_str()).collect();
+        keys.sort();
+        keys
+    }
+
+    fn timestamp(offset: Duration) -> String {
+        (Utc::now() + offset).to_rfc3339()
+    }
+
+    #[test]
+    fn expands_transitively() {
+        let hierarchy = FeatureHierarchy::new()
+            .implies("enterprise", ["premium", "sso"])
+            .implies("premium", ["export"]);
+        let expanded = hierarchy.expand(&[Feature::named("enterprise")]);
+        assert_eq!(keys(&expanded), ["enterprise", "export", "premium", "sso"]);
+    }
+
+    #[test]
+    fn combines_server_and_declared_implications() {
+        let enterprise = Feature {
+            implies: vec!["audit".to_string()],
+            ..Feature::named("enterprise")
+        };
+        let hierarchy = FeatureHierarchy::new().implies("enterprise", ["sso"]);
+        assert_eq!(keys(&hierarchy.expand(&[enterprise])), ["audit", "enterprise", "sso"]);
+    }
+
+    #[test]
+    fn cycles_terminate_and_list_each_feature_once() {
+        let hierarchy = FeatureHierarchy::new()
+            .implies("a", ["b"])
+            .implies("b", ["c"])
+            .implies("c", ["a"]);
+        let expanded = hierarchy.expand(&[Feature::named("a"), Feature::named("c")]);
+        assert_eq!(keys(&expanded), ["a", "b", "c"]);
+
+        let hierarchy = FeatureHierarchy::new().implies("a", ["a"]);
+        assert_eq!(keys(&hierarchy.expand(&[Feature::named("a")])), ["a"]);
+    }
+
+    #[test]
+    fn inactive_features_imply_nothing() {
+        let hierarchy = FeatureHierarchy::new().implies("premium", ["export"]);
+
+        let disabled = Feature {
+            enabled: false,
+            ..Feature::named("premium")
+        };
+        let expired = Feature {
+            expires_at: Some(timestamp(-Duration::hours(1))),
+            ..Feature::named("premium")
+        };
+        let not_started = Feature {
+            starts_at: Some(timestamp(Duration::hours(1))),
+            ..Feature::named("premium")
+        };
+        let unparseable = Feature {
+            expires_at: Some("soon".to_string()),
+            ..Feature::named("premium")
+        };
+
+        for feature in [disabled, expired, not_started, unparseable] {
+            assert!(hierarchy.expand(&[feature]).is_empty());
+        }
+    }
+
+    #[test]
+    fn implied_features_ignore_their_own_window() {
+        let hierarchy = FeatureHierarchy::new().implies("premium", ["export"]);
+        let premium = Feature {
+            expires_at: Some(timestamp(Duration::days(30))),
+            ..Feature::named("premium")
+        };
+        let export = Feature {
+            name: "PDF Export".to_string(),
+            enabled: false,
+            expires_at: Some(timestamp(-Duration::days(1))),
+            ..Feature::named("export")
+        };
+
+        let expanded = hierarchy.expand(&[premium, export]);
+        let export = expanded.iter().find(|f| f.key == "export").unwrap();
+        assert!(export.is_active());
+        assert_eq!(export.name, "PDF Export");
+        assert_eq!(export.expires_at, None);
+    }
+
+    #[test]
+    fn implied_features_take_the_implying_source() {
+        let hierarchy = FeatureHierarchy::new().implies("premium", ["export"]);
+        let source = FeatureSource::AddOn {
+            license_key: "ADDON-KEY".to_string(),
+        };
+        let premium = Feature {
+            source: source.clone(),
+            ..Feature::named("premium")
+        };
+
+        let expanded = hierarchy.expand(&[premium]);
+        let export = expanded.iter().find(|f| f.key == "export").unwrap();
+        assert_eq!(export.name, "export");
+        assert_eq!(export.source, source);
+    }
+
+    #[test]
+    fn date_only_windows_cover_the_whole_day() {
+        let hierarchy = FeatureHierarchy::new().implies("premium", ["export"]);
+        let today = Utc::now().date_naive().to_string();
+        let premium = Feature {
+            starts_at: Some(today.clone()),
+            expires_at: Some(today),
+            ..Feature::named("premium")
+        };
+        assert_eq!(keys(&hierarchy.expand(&[premium])), ["export", "premium"]);
+    }
+}
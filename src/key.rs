@@ -0,0 +1,213 @@
+use crate::error::{LicenseError, Result};
+use std::fmt;
+use std::str::FromStr;
+
+/// Characters per dash-separated group.
+const GROUP_LEN: usize = 4;
+
+const MIN_LEN: usize = 8;
+const MAX_LEN: usize = 64;
+
+/// Most characters left visible at the end of a redacted key.
+const REDACTED_SUFFIX_LEN: usize = 4;
+
+/// Alphabet for the mod-36 Luhn check character.
+const ALPHABET: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+/// A normalized license key such as `IRON-ABCD-EFGH-IJKL-MNOP`.
+///
+/// Parsing trims whitespace, uppercases, and regroups the characters into
+/// dash-separated groups of four, so `" iron abcd-efghijkl mnop "` and
+/// `"IRON-ABCD-EFGH-IJKL-MNOP"` are the same key. `Debug` output is redacted;
+/// use [`as_str`](Self::as_str) or `Display` for the full key.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct LicenseKey(String);
+
+impl LicenseKey {
+    /// Parse and normalize a license key.
+    pub fn parse(input: &str) -> Result<Self> {
+        let mut chars = String::with_capacity(input.len());
+        for c in input.chars() {
+            match c {
+                '-' => {}
+                c if c.is_whitespace() => {}
+                c if c.is_ascii_alphanumeric() => chars.push(c.to_ascii_uppercase()),
+                c => {
+                    return Err(LicenseError::InvalidLicenseKey(format!(
+                        "unexpected character '{}'",
+                        c
+                    )))
+                }
+            }
+        }
+
+        if chars.len() < MIN_LEN || chars.len() > MAX_LEN {
+            return Err(LicenseError::InvalidLicenseKey(format!(
+                "expected {} to {} characters, got {}",
+                MIN_LEN,
+                MAX_LEN,
+                chars.len()
+            )));
+        }
+
+        let groups: Vec<&str> = chars
+            .as_bytes()
+            .chunks(GROUP_LEN)
+            .map(|g| std::str::from_utf8(g).unwrap_or_default())
+            .collect();
+        Ok(Self(groups.join("-")))
+    }
+
+    /// The normalized key.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Check the key's final character against a mod-36 Luhn checksum of
+    /// the preceding characters. Catches single-character typos and most
+    /// adjacent transpositions.
+    pub fn has_valid_checksum(&self) -> bool {
+        let chars: Vec<u8> = self.0.bytes().filter(|&b| b != b'-').collect();
+        match chars.split_last() {
+            Some((&check, body)) => check_char(body) == Some(check),
+            None => false,
+        }
+    }
+
+    /// The key with every character masked except the last four, or the
+    /// last quarter of shorter keys, safe for logs.
+    pub fn redacted(&self) -> String {
+        let len = self.0.bytes().filter(|&b| b != b'-').count();
+        let visible = REDACTED_SUFFIX_LEN.min(len / 4);
+
+        let mut remaining = len;
+        self.0
+            .chars()
+            .map(|c| match c {
+                '-' => '-',
+                c => {
+                    remaining -= 1;
+                    if remaining < visible {
+                        c
+                    } else {
+                        '*'
+                    }
+                }
+            })
+            .collect()
+    }
+}
+
+/// Compute the mod-36 Luhn check character for `body`.
+fn check_char(body: &[u8]) -> Option<u8> {
+    let n = ALPHABET.len() as u32;
+    let mut sum = 0;
+    for (i, &b) in body.iter().rev().enumerate() {
+        let mut value = ALPHABET.iter().position(|&a| a == b)? as u32;
+        if i.is_multiple_of(2) {
+            value *= 2;
+            value = value / n + value % n;
+        }
+        sum += value;
+    }
+    Some(ALPHABET[((n - sum % n) % n) as usize])
+}
+
+impl FromStr for LicenseKey {
+    type Err = LicenseError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::parse(s)
+    }
+}
+
+impl TryFrom<&str> for LicenseKey {
+    type Error = LicenseError;
+
+    fn try_from(s: &str) -> Result<Self> {
+        Self::parse(s)
+    }
+}
+
+impl AsRef<str> for LicenseKey {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for LicenseKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl fmt::Debug for LicenseKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("LicenseKey").field(&self.redacted()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_check_char(body: &str) -> String {
+        let check = check_char(body.as_bytes()).unwrap() as char;
+        format!("{}{}", body, check)
+    }
+
+    #[test]
+    fn parse_normalizes_key() {
+        let key = LicenseKey::parse(" iron abcd-efghijkl mnop ").unwrap();
+        assert_eq!(key.as_str(), "IRON-ABCD-EFGH-IJKL-MNOP");
+    }
+
+    #[test]
+    fn parse_rejects_bad_input() {
+        assert!(LicenseKey::parse("IRON_ABCD").is_err());
+        assert!(LicenseKey::parse("ABC").is_err());
+        assert!(LicenseKey::parse(&"A".repeat(MAX_LEN + 1)).is_err());
+    }
+
+    #[test]
+    fn checksum_accepts_valid_keys() {
+        for body in ["IRONABCDEFGHIJKLMNO", "0000000", "ZZZZZZZZZZZ"] {
+            let key = LicenseKey::parse(&with_check_char(body)).unwrap();
+            assert!(key.has_valid_checksum(), "{}", key.as_str());
+        }
+    }
+
+    #[test]
+    fn checksum_catches_typos_and_transpositions() {
+        let valid = with_check_char("IRONABCDEFGHIJKLMNO");
+
+        let mut typo = valid.clone().into_bytes();
+        typo[5] = if typo[5] == b'X' { b'Y' } else { b'X' };
+        let typo = LicenseKey::parse(std::str::from_utf8(&typo).unwrap()).unwrap();
+        assert!(!typo.has_valid_checksum());
+
+        let mut swapped = valid.into_bytes();
+        swapped.swap(6, 7);
+        let swapped = LicenseKey::parse(std::str::from_utf8(&swapped).unwrap()).unwrap();
+        assert!(!swapped.has_valid_checksum());
+    }
+
+    #[test]
+    fn checksum_rejects_characters_outside_alphabet() {
+        assert_eq!(check_char(b"ABC!"), None);
+    }
+
+    #[test]
+    fn redacted_keeps_only_a_short_suffix() {
+        let redacted = |key: &str| LicenseKey::parse(key).unwrap().redacted();
+        assert_eq!(redacted("IRON-ABCD-EFGH-IJKL-MNOP"), "****-****-****-****-MNOP");
+        assert_eq!(redacted("ABCD-EFGH-IJKL"), "****-****-*JKL");
+        assert_eq!(redacted("ABCD-EFGH"), "****-**GH");
+    }
+
+    #[test]
+    fn debug_is_redacted() {
+        let key = LicenseKey::parse("ABCD-EFGH-IJKL-MNOP").unwrap();
+        assert!(!format!("{:?}", key).contains("ABCD"));
+    }
+}
@@ -54,17 +54,64 @@
 //!     Ok(())
 //! }
 //! ```
+//!
+//! # Feature Gating
+//!
+//! Gate code on a feature with [`feature_gate!`], a [`FeatureGuard`], or the
+//! `#[requires_feature]` attribute (enable the `macros` feature):
+//!
+//! ```rust,no_run
+//! use ironlicensing::{feature_gate, FeatureGuard};
+//!
+//! fn export() -> ironlicensing::Result<()> {
+//!     let _guard = FeatureGuard::acquire("export-pdf")?;
+//!     // Feature is available while the guard is in scope
+//!     Ok(())
+//! }
+//!
+//! let max_rows = feature_gate!("premium", { 10_000 } else { 100 });
+//! ```
 
+mod analytics;
+mod cache;
+mod callback;
 mod client;
 mod config;
+#[cfg(all(feature = "dev-override", debug_assertions))]
+mod dev;
 mod error;
+mod events;
+mod fingerprint;
+mod guard;
+mod hierarchy;
+mod key;
+mod money;
+mod registry;
+mod secrets;
+mod storage;
+mod time;
 mod transport;
 mod types;
+mod version;
 
+pub use callback::{CheckoutCallback, CheckoutRedirect};
 pub use client::LicenseClient;
-pub use config::LicenseOptions;
+pub use config::{Environment, LicenseConfig, LicenseOptions};
 pub use error::{LicenseError, Result};
+pub use events::LicenseEvent;
+pub use guard::FeatureGuard;
+pub use hierarchy::FeatureHierarchy;
+pub use key::LicenseKey;
+pub use money::{BillingPeriod, Money, RegionalPrice};
+pub use registry::{FeatureKey, FeatureRegistryReport};
+pub use secrets::{EncryptedFileStore, SecretBackend, SecretStore};
+#[cfg(all(target_os = "linux", feature = "secret-service"))]
+pub use secrets::SecretServiceStore;
 pub use types::*;
+pub use version::BuildVersion;
+
+#[cfg(feature = "macros")]
+pub use ironlicensing_macros::{requires_feature, FeatureKey};
 
 use once_cell::sync::OnceCell;
 use std::sync::Arc;
@@ -84,6 +131,11 @@ pub fn init_with_options(options: LicenseOptions) -> Result<()> {
         .map_err(|_| LicenseError::Api("Client already initialized".to_string()))
 }
 
+/// Initialize the global client from `IRONLICENSING_*` environment variables.
+pub fn init_from_env() -> Result<()> {
+    init_with_options(LicenseOptions::from_env()?)
+}
+
 /// Get the global client.
 pub fn get_client() -> Result<&'static Arc<LicenseClient>> {
     GLOBAL_CLIENT.get().ok_or(LicenseError::NotInitialized)
@@ -109,23 +161,101 @@ pub fn deactivate() -> Result<bool> {
     Ok(get_client()?.deactivate())
 }
 
+/// Transfer an activation from a previous machine ID using the global client.
+pub fn transfer_activation() -> Result<LicenseResult> {
+    Ok(get_client()?.transfer_activation())
+}
+
 /// Start a trial using the global client.
 pub fn start_trial(email: &str) -> Result<LicenseResult> {
     Ok(get_client()?.start_trial(email))
 }
 
+/// Start an anonymous trial using the global client.
+pub fn start_anonymous_trial() -> Result<LicenseResult> {
+    Ok(get_client()?.start_anonymous_trial())
+}
+
+/// Get the trial status using the global client.
+pub fn trial_status() -> Result<TrialStatus> {
+    get_client()?.trial_status()
+}
+
+/// Extend the current trial using the global client.
+pub fn extend_trial(code: &str) -> Result<LicenseResult> {
+    Ok(get_client()?.extend_trial(code))
+}
+
+/// Convert the current trial to a full license using the global client.
+pub fn convert_trial(license_key: &str) -> Result<LicenseResult> {
+    Ok(get_client()?.convert_trial(license_key))
+}
+
+/// Record a usage event using the global client.
+pub fn track_event(name: &str, properties: std::collections::HashMap<String, serde_json::Value>) -> Result<()> {
+    get_client()?.track_event(name, properties);
+    Ok(())
+}
+
+/// Send queued usage events using the global client.
+pub fn flush_analytics() -> Result<()> {
+    get_client()?.flush_analytics()
+}
+
+/// Turn usage analytics on or off on the global client.
+pub fn set_analytics_enabled(enabled: bool) -> Result<()> {
+    get_client()?.set_analytics_enabled(enabled);
+    Ok(())
+}
+
+/// Get undismissed server messages using the global client.
+pub fn pending_messages() -> Result<Vec<ServerMessage>> {
+    Ok(get_client()?.pending_messages())
+}
+
+/// Dismiss a server message using the global client.
+pub fn dismiss_message(id: &str) -> Result<()> {
+    get_client()?.dismiss_message(id);
+    Ok(())
+}
+
+/// Subscribe to license state changes on the global client.
+pub fn subscribe() -> Result<std::sync::mpsc::Receiver<LicenseEvent>> {
+    Ok(get_client()?.subscribe())
+}
+
 /// Check if a feature is available using the global client.
-pub fn has_feature(feature_key: &str) -> Result<bool> {
+pub fn has_feature(feature_key: impl AsRef<str>) -> Result<bool> {
     Ok(get_client()?.has_feature(feature_key))
 }
 
 /// Require a feature using the global client.
-pub fn require_feature(feature_key: &str) -> Result<()> {
+pub fn require_feature(feature_key: impl AsRef<str>) -> Result<()> {
     get_client()?.require_feature(feature_key)
 }
 
+/// Activate an add-on license key using the global client.
+pub fn activate_addon(license_key: &str) -> Result<LicenseResult> {
+    Ok(get_client()?.activate_addon(license_key))
+}
+
+/// Deactivate an add-on license key using the global client.
+pub fn deactivate_addon(license_key: &str) -> Result<bool> {
+    Ok(get_client()?.deactivate_addon(license_key))
+}
+
+/// Get the activated add-on licenses using the global client.
+pub fn addons() -> Result<Vec<License>> {
+    Ok(get_client()?.addons())
+}
+
+/// Get enabled and implied features using the global client.
+pub fn effective_features() -> Result<Vec<Feature>> {
+    Ok(get_client()?.effective_features())
+}
+
 /// Get a feature using the global client.
-pub fn get_feature(feature_key: &str) -> Result<Option<Feature>> {
+pub fn get_feature(feature_key: impl AsRef<str>) -> Result<Option<Feature>> {
     Ok(get_client()?.get_feature(feature_key))
 }
 
@@ -149,12 +279,77 @@ pub fn is_trial() -> Result<bool> {
     Ok(get_client()?.is_trial())
 }
 
+/// Check if a build is covered by the updates entitlement using the global client.
+pub fn is_version_allowed(build_date_or_semver: &str) -> Result<bool> {
+    Ok(get_client()?.is_version_allowed(build_date_or_semver))
+}
+
+/// Check if the subscription is in its payment grace period using the global client.
+pub fn is_in_dunning() -> Result<bool> {
+    Ok(get_client()?.is_in_dunning())
+}
+
+/// Get the subscription's payment state using the global client.
+pub fn payment_state() -> Result<PaymentState> {
+    Ok(get_client()?.payment_state())
+}
+
+/// Get the next renewal date using the global client.
+pub fn renews_at() -> Result<Option<String>> {
+    Ok(get_client()?.renews_at())
+}
+
+/// Get the customer portal URL using the global client.
+pub fn customer_portal_url() -> Result<String> {
+    get_client()?.customer_portal_url()
+}
+
 /// Get available tiers using the global client.
 pub fn get_tiers() -> Result<Vec<ProductTier>> {
     Ok(get_client()?.get_tiers())
 }
 
+/// Get available tiers using the global client, failing if they can't be fetched.
+pub fn try_get_tiers() -> Result<Vec<ProductTier>> {
+    get_client()?.try_get_tiers()
+}
+
+/// Compare a feature registry with the server using the global client.
+pub fn check_feature_registry<K: FeatureKey>() -> Result<FeatureRegistryReport> {
+    get_client()?.check_feature_registry::<K>()
+}
+
 /// Start a purchase using the global client.
 pub fn start_purchase(tier_id: &str, email: &str) -> Result<CheckoutResult> {
     Ok(get_client()?.start_purchase(tier_id, email))
 }
+
+/// Get the tier of the current license using the global client.
+pub fn current_tier() -> Result<Option<ProductTier>> {
+    Ok(get_client()?.current_tier())
+}
+
+/// Get upgrade and downgrade options using the global client.
+pub fn upgrade_options() -> Result<Vec<UpgradeOption>> {
+    Ok(get_client()?.upgrade_options())
+}
+
+/// Start an upgrade checkout for the current license using the global client.
+pub fn start_upgrade(tier_id: &str) -> Result<CheckoutResult> {
+    Ok(get_client()?.start_upgrade(tier_id))
+}
+
+/// Get the current state of a checkout session using the global client.
+pub fn poll_checkout(session_id: &str) -> Result<CheckoutSession> {
+    get_client()?.poll_checkout(session_id)
+}
+
+/// Wait for a checkout session to finish using the global client.
+pub fn wait_for_checkout(session_id: &str, timeout: std::time::Duration) -> Result<CheckoutSession> {
+    get_client()?.wait_for_checkout(session_id, timeout)
+}
+
+/// Wait for a checkout to complete and activate the issued license using the global client.
+pub fn wait_for_checkout_and_activate(session_id: &str, timeout: std::time::Duration) -> Result<LicenseResult> {
+    get_client()?.wait_for_checkout_and_activate(session_id, timeout)
+}
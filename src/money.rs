@@ -0,0 +1,297 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::fmt;
+
+/// A monetary amount in the currency's minor unit (e.g. cents).
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Money {
+    amount_minor: i64,
+    currency: String,
+}
+
+impl Money {
+    /// Create an amount from minor units, e.g. `Money::from_minor(1999, "USD")` for $19.99.
+    pub fn from_minor(amount_minor: i64, currency: impl Into<String>) -> Self {
+        Self {
+            amount_minor,
+            currency: currency.into().to_uppercase(),
+        }
+    }
+
+    /// Create an amount from major units, rounding to the currency's minor unit.
+    pub fn from_major(amount: f64, currency: impl Into<String>) -> Self {
+        let currency = currency.into().to_uppercase();
+        let scale = 10f64.powi(minor_digits(&currency) as i32);
+        Self {
+            amount_minor: (amount * scale).round() as i64,
+            currency,
+        }
+    }
+
+    /// The amount in minor units.
+    pub fn amount_minor(&self) -> i64 {
+        self.amount_minor
+    }
+
+    /// The ISO 4217 currency code.
+    pub fn currency(&self) -> &str {
+        &self.currency
+    }
+
+    /// Number of digits after the decimal point for this currency.
+    pub fn minor_digits(&self) -> u32 {
+        minor_digits(&self.currency)
+    }
+
+    /// The amount in major units. Lossy; use only for display or arithmetic
+    /// where precision doesn't matter.
+    pub fn as_major(&self) -> f64 {
+        self.amount_minor as f64 / 10f64.powi(self.minor_digits() as i32)
+    }
+
+    /// Check if the amount is zero.
+    pub fn is_zero(&self) -> bool {
+        self.amount_minor == 0
+    }
+
+    /// Format the amount for a locale such as `"en-US"` or `"de-DE"`.
+    ///
+    /// Covers symbol placement, decimal and grouping separators for common
+    /// locales; unknown locales fall back to `en-US` conventions.
+    pub fn format(&self, locale: &str) -> String {
+        let style = LocaleStyle::for_locale(locale);
+        let number = self.format_number(style.decimal, style.group);
+        let sign = if self.amount_minor < 0 { "-" } else { "" };
+        let symbol = currency_symbol(&self.currency);
+
+        match (style.symbol_first, symbol) {
+            (true, Some(symbol)) if symbol.ends_with(char::is_alphabetic) => {
+                format!("{}{}\u{a0}{}", sign, symbol, number)
+            }
+            (true, Some(symbol)) => format!("{}{}{}", sign, symbol, number),
+            (false, Some(symbol)) => format!("{}{}\u{a0}{}", sign, number, symbol),
+            (_, None) => format!("{}{}\u{a0}{}", sign, number, self.currency),
+        }
+    }
+
+    fn format_number(&self, decimal: char, group: char) -> String {
+        let digits = self.minor_digits();
+        let scale = 10u64.pow(digits);
+        let abs = self.amount_minor.unsigned_abs();
+        let whole = (abs / scale).to_string();
+
+        let mut grouped = String::new();
+        for (i, c) in whole.chars().enumerate() {
+            if i > 0 && (whole.len() - i).is_multiple_of(3) {
+                grouped.push(group);
+            }
+            grouped.push(c);
+        }
+
+        if digits > 0 {
+            format!("{}{}{:0width$}", grouped, decimal, abs % scale, width = digits as usize)
+        } else {
+            grouped
+        }
+    }
+}
+
+impl PartialOrd for Money {
+    /// Amounts in different currencies are not comparable.
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        (self.currency == other.currency).then(|| self.amount_minor.cmp(&other.amount_minor))
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.amount_minor < 0 { "-" } else { "" };
+        write!(f, "{}{} {}", sign, self.format_number('.', ','), self.currency)
+    }
+}
+
+/// Billing period of a product tier.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub enum BillingPeriod {
+    Monthly,
+    Yearly,
+    #[default]
+    Lifetime,
+    /// A period the SDK doesn't recognize, kept as sent by the server.
+    Other(String),
+}
+
+impl BillingPeriod {
+    /// Parse a billing period as sent by the API. A missing period means a
+    /// one-time purchase.
+    pub fn parse(value: Option<&str>) -> Self {
+        match value.map(|v| v.trim().to_lowercase()).as_deref() {
+            None | Some("") | Some("lifetime") | Some("once") | Some("one_time") => Self::Lifetime,
+            Some("month") | Some("monthly") => Self::Monthly,
+            Some("year") | Some("yearly") | Some("annual") | Some("annually") => Self::Yearly,
+            Some(other) => Self::Other(other.to_string()),
+        }
+    }
+
+    /// The API representation of this period.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Monthly => "monthly",
+            Self::Yearly => "yearly",
+            Self::Lifetime => "lifetime",
+            Self::Other(other) => other,
+        }
+    }
+
+    /// Check if the period recurs.
+    pub fn is_recurring(&self) -> bool {
+        matches!(self, Self::Monthly | Self::Yearly)
+    }
+
+    /// Number of billing periods in a year, for recurring periods.
+    pub fn periods_per_year(&self) -> Option<i64> {
+        match self {
+            Self::Monthly => Some(12),
+            Self::Yearly => Some(1),
+            _ => None,
+        }
+    }
+}
+
+/// A price for a specific country.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegionalPrice {
+    /// ISO 3166-1 alpha-2 country code.
+    pub country: String,
+    #[serde(flatten)]
+    pub price: Money,
+}
+
+struct LocaleStyle {
+    decimal: char,
+    group: char,
+    symbol_first: bool,
+}
+
+impl LocaleStyle {
+    fn for_locale(locale: &str) -> Self {
+        let locale = locale.replace('_', "-").to_lowercase();
+        let language = locale.split('-').next().unwrap_or_default();
+
+        match (language, locale.as_str()) {
+            (_, "de-ch") => Self::new('.', '\'', true),
+            ("de" | "es" | "it" | "nl" | "pt" | "id" | "tr" | "da", _) => Self::new(',', '.', false),
+            ("fr" | "pl" | "cs" | "sv" | "nb" | "fi" | "ru" | "uk", _) => Self::new(',', '\u{a0}', false),
+            _ => Self::new('.', ',', true),
+        }
+    }
+
+    fn new(decimal: char, group: char, symbol_first: bool) -> Self {
+        Self {
+            decimal,
+            group,
+            symbol_first,
+        }
+    }
+}
+
+fn minor_digits(currency: &str) -> u32 {
+    match currency {
+        "BIF" | "CLP" | "DJF" | "GNF" | "ISK" | "JPY" | "KMF" | "KRW" | "PYG" | "RWF" | "UGX" | "VND"
+        | "VUV" | "XAF" | "XOF" | "XPF" => 0,
+        "BHD" | "IQD" | "JOD" | "KWD" | "LYD" | "OMR" | "TND" => 3,
+        _ => 2,
+    }
+}
+
+fn currency_symbol(currency: &str) -> Option<&'static str> {
+    Some(match currency {
+        "USD" => "$",
+        "EUR" => "€",
+        "GBP" => "£",
+        "JPY" => "¥",
+        "CNY" => "CN¥",
+        "INR" => "₹",
+        "KRW" => "₩",
+        "BRL" => "R$",
+        "CAD" => "CA$",
+        "AUD" => "A$",
+        "CHF" => "CHF",
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_places_symbol_and_separators_by_locale() {
+        let price = Money::from_minor(123_456_789, "USD");
+        assert_eq!(price.format("en-US"), "$1,234,567.89");
+
+        let price = Money::from_minor(123_456, "EUR");
+        assert_eq!(price.format("de-DE"), "1.234,56\u{a0}€");
+        assert_eq!(price.format("fr_FR"), "1\u{a0}234,56\u{a0}€");
+
+        let price = Money::from_minor(100_050, "CHF");
+        assert_eq!(price.format("de-CH"), "CHF\u{a0}1'000.50");
+    }
+
+    #[test]
+    fn format_falls_back_to_en_us_and_currency_code() {
+        let price = Money::from_minor(1_999, "USD");
+        assert_eq!(price.format("xx-YY"), "$19.99");
+        assert_eq!(price.format(""), "$19.99");
+
+        let price = Money::from_minor(250_000, "SEK");
+        assert_eq!(price.format("sv-SE"), "2\u{a0}500,00\u{a0}SEK");
+    }
+
+    #[test]
+    fn format_respects_minor_digits() {
+        assert_eq!(Money::from_minor(1_500, "JPY").format("en-US"), "¥1,500");
+        assert_eq!(Money::from_minor(1_234_567, "KWD").format("en-US"), "1,234.567\u{a0}KWD");
+        assert_eq!(Money::from_minor(5, "KWD").format("de-DE"), "0,005\u{a0}KWD");
+        assert_eq!(Money::from_minor(0, "USD").format("en-US"), "$0.00");
+    }
+
+    #[test]
+    fn format_puts_sign_before_symbol() {
+        assert_eq!(Money::from_minor(-1_999, "USD").format("en-US"), "-$19.99");
+        assert_eq!(Money::from_minor(-5, "EUR").format("de-DE"), "-0,05\u{a0}€");
+        assert_eq!(Money::from_minor(-1_000, "JPY").format("en-US"), "-¥1,000");
+        assert_eq!(Money::from_minor(i64::MIN, "JPY").format("en-US"), "-¥9,223,372,036,854,775,808");
+    }
+
+    #[test]
+    fn format_number_groups_thousands() {
+        let number = |minor, currency| Money::from_minor(minor, currency).format_number('.', ',');
+        assert_eq!(number(99_999, "USD"), "999.99");
+        assert_eq!(number(100_000, "USD"), "1,000.00");
+        assert_eq!(number(100_000_000, "JPY"), "100,000,000");
+        assert_eq!(number(1, "USD"), "0.01");
+    }
+
+    #[test]
+    fn display_uses_currency_code() {
+        assert_eq!(Money::from_minor(-123_456, "usd").to_string(), "-1,234.56 USD");
+        assert_eq!(Money::from_minor(1_500, "JPY").to_string(), "1,500 JPY");
+    }
+
+    #[test]
+    fn from_major_rounds_to_minor_unit() {
+        assert_eq!(Money::from_major(0.1 + 0.2, "USD").amount_minor(), 30);
+        assert_eq!(Money::from_major(1499.6, "JPY").amount_minor(), 1500);
+        assert_eq!(Money::from_major(-2.5, "KWD").amount_minor(), -2500);
+    }
+
+    #[test]
+    fn amounts_in_different_currencies_are_not_comparable() {
+        let usd = Money::from_minor(100, "USD");
+        assert_eq!(usd.partial_cmp(&Money::from_minor(200, "USD")), Some(Ordering::Less));
+        assert_eq!(usd.partial_cmp(&Money::from_minor(100, "EUR")), None);
+    }
+}
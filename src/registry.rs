@@ -0,0 +1,62 @@
+use std::collections::BTreeSet;
+
+/// A product's features, declared once as an enum so keys are checked at
+/// compile time.
+///
+/// With the `macros` feature, derive it on a fieldless enum. Keys default to
+/// the variant name in snake_case; override them with `#[ironlicensing(key = "...")]`:
+///
+/// ```ignore
+/// #[derive(Clone, Copy, ironlicensing::FeatureKey)]
+/// enum Features {
+///     Premium,
+///     #[ironlicensing(key = "cloud-sync")]
+///     CloudSync,
+/// }
+///
+/// client.has_feature(Features::CloudSync);
+/// ```
+pub trait FeatureKey: Copy + AsRef<str> + 'static {
+    /// Every declared feature.
+    const ALL: &'static [Self];
+
+    /// The key the server uses for this feature.
+    fn key(self) -> &'static str;
+
+    /// Look up a declared feature by key.
+    fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|f| f.key() == key)
+    }
+}
+
+/// Differences between a [`FeatureKey`] registry and the features the
+/// server returns for the product.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FeatureRegistryReport {
+    /// Features the server returned that aren't declared in the registry.
+    pub undeclared: Vec<String>,
+    /// Declared features the server never returned.
+    pub unused: Vec<&'static str>,
+}
+
+impl FeatureRegistryReport {
+    /// Compare the registry `K` with the feature keys seen from the server.
+    pub fn compare<'a, K: FeatureKey>(server_keys: impl IntoIterator<Item = &'a str>) -> Self {
+        let server: BTreeSet<&str> = server_keys.into_iter().collect();
+        let declared: BTreeSet<&'static str> = K::ALL.iter().map(|f| f.key()).collect();
+
+        Self {
+            undeclared: server
+                .iter()
+                .filter(|k| !declared.contains(*k))
+                .map(|k| k.to_string())
+                .collect(),
+            unused: declared.into_iter().filter(|k| !server.contains(k)).collect(),
+        }
+    }
+
+    /// Check if the registry and the server agree.
+    pub fn is_consistent(&self) -> bool {
+        self.undeclared.is_empty() && self.unused.is_empty()
+    }
+}
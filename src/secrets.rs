@@ -0,0 +1,201 @@
+use crate::error::{LicenseError, Result};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Domain separator for deriving the file store's encryption key.
+const KEY_CONTEXT: &[u8] = b"ironlicensing-secret-store-v1";
+
+/// Length of the ChaCha20-Poly1305 nonce prepended to each file.
+const NONCE_LEN: usize = 12;
+
+/// Storage for sensitive SDK state such as license keys and cached licenses.
+pub trait SecretStore: Send + Sync {
+    /// Read a secret, or `None` if it doesn't exist.
+    fn get(&self, name: &str) -> Result<Option<Vec<u8>>>;
+
+    /// Create or replace a secret.
+    fn set(&self, name: &str, value: &[u8]) -> Result<()>;
+
+    /// Delete a secret. Deleting a missing secret is not an error.
+    fn delete(&self, name: &str) -> Result<()>;
+}
+
+/// Which [`SecretStore`] the client uses.
+#[derive(Clone, Default)]
+pub enum SecretBackend {
+    /// Files encrypted with a key derived from the OS machine ID and
+    /// product slug.
+    #[default]
+    EncryptedFile,
+    /// The Secret Service D-Bus API (GNOME Keyring, KWallet).
+    #[cfg(all(target_os = "linux", feature = "secret-service"))]
+    SecretService,
+    /// A caller-provided store.
+    Custom(Arc<dyn SecretStore>),
+}
+
+impl fmt::Debug for SecretBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::EncryptedFile => f.write_str("EncryptedFile"),
+            #[cfg(all(target_os = "linux", feature = "secret-service"))]
+            Self::SecretService => f.write_str("SecretService"),
+            Self::Custom(_) => f.write_str("Custom"),
+        }
+    }
+}
+
+/// A [`SecretStore`] that keeps each secret in its own encrypted file.
+///
+/// Files are bound to this machine and product: copying them to another
+/// machine or reading them with another product slug fails to decrypt.
+/// The key comes from the OS-assigned machine ID (`/etc/machine-id` on
+/// Linux, `IOPlatformUUID` on macOS, `MachineGuid` on Windows).
+pub struct EncryptedFileStore {
+    dir: PathBuf,
+    cipher: ChaCha20Poly1305,
+}
+
+impl EncryptedFileStore {
+    /// Create a store in `dir` keyed to this machine and `product_slug`.
+    ///
+    /// Fails if the OS provides no stable machine ID to derive the key from.
+    pub fn new(dir: impl Into<PathBuf>, product_slug: &str) -> Result<Self> {
+        let fingerprint = crate::fingerprint::machine_fingerprint().ok_or_else(|| {
+            LicenseError::SecretStore("no stable machine ID to derive the encryption key from; use another secret backend or disable the offline cache".to_string())
+        })?;
+
+        let mut hasher = Sha256::new();
+        hasher.update(KEY_CONTEXT);
+        hasher.update(fingerprint.as_bytes());
+        hasher.update([0]);
+        hasher.update(product_slug.as_bytes());
+        let key = hasher.finalize();
+
+        Ok(Self {
+            dir: dir.into(),
+            cipher: ChaCha20Poly1305::new(Key::from_slice(&key)),
+        })
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{}.enc", name))
+    }
+}
+
+impl SecretStore for EncryptedFileStore {
+    fn get(&self, name: &str) -> Result<Option<Vec<u8>>> {
+        let data = match fs::read(self.path(name)) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        if data.len() < NONCE_LEN {
+            return Err(LicenseError::SecretStore(format!("'{}' is truncated", name)));
+        }
+        let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+        self.cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map(Some)
+            .map_err(|_| LicenseError::SecretStore(format!("'{}' could not be decrypted", name)))
+    }
+
+    fn set(&self, name: &str, value: &[u8]) -> Result<()> {
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, value)
+            .map_err(|_| LicenseError::SecretStore(format!("'{}' could not be encrypted", name)))?;
+
+        let mut data = nonce.to_vec();
+        data.extend_from_slice(&ciphertext);
+
+        crate::storage::write_private(&self.path(name), &data)?;
+        Ok(())
+    }
+
+    fn delete(&self, name: &str) -> Result<()> {
+        crate::storage::remove(&self.path(name))?;
+        Ok(())
+    }
+}
+
+/// A [`SecretStore`] backed by the Secret Service D-Bus API.
+#[cfg(all(target_os = "linux", feature = "secret-service"))]
+pub struct SecretServiceStore {
+    product_slug: String,
+}
+
+#[cfg(all(target_os = "linux", feature = "secret-service"))]
+impl SecretServiceStore {
+    /// Create a store whose items are tagged with `product_slug`.
+    pub fn new(product_slug: impl Into<String>) -> Self {
+        Self {
+            product_slug: product_slug.into(),
+        }
+    }
+
+    fn connect() -> Result<secret_service::blocking::SecretService<'static>> {
+        secret_service::blocking::SecretService::connect(secret_service::EncryptionType::Dh).map_err(Self::error)
+    }
+
+    fn attributes<'a>(&'a self, name: &'a str) -> std::collections::HashMap<&'a str, &'a str> {
+        [
+            ("application", "ironlicensing"),
+            ("product", self.product_slug.as_str()),
+            ("name", name),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    fn error(e: secret_service::Error) -> LicenseError {
+        LicenseError::SecretStore(e.to_string())
+    }
+}
+
+#[cfg(all(target_os = "linux", feature = "secret-service"))]
+impl SecretStore for SecretServiceStore {
+    fn get(&self, name: &str) -> Result<Option<Vec<u8>>> {
+        let service = Self::connect()?;
+        let items = service.search_items(self.attributes(name)).map_err(Self::error)?;
+        match items.unlocked.first().or(items.locked.first()) {
+            Some(item) => {
+                item.ensure_unlocked().map_err(Self::error)?;
+                item.get_secret().map(Some).map_err(Self::error)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn set(&self, name: &str, value: &[u8]) -> Result<()> {
+        let service = Self::connect()?;
+        let collection = service.get_default_collection().map_err(Self::error)?;
+        collection.ensure_unlocked().map_err(Self::error)?;
+        collection
+            .create_item(
+                &format!("IronLicensing {} ({})", name, self.product_slug),
+                self.attributes(name),
+                value,
+                true,
+                "application/octet-stream",
+            )
+            .map_err(Self::error)?;
+        Ok(())
+    }
+
+    fn delete(&self, name: &str) -> Result<()> {
+        let service = Self::connect()?;
+        let items = service.search_items(self.attributes(name)).map_err(Self::error)?;
+        for item in items.unlocked.iter().chain(items.locked.iter()) {
+            item.delete().map_err(Self::error)?;
+        }
+        Ok(())
+    }
+}
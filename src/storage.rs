@@ -0,0 +1,284 @@
+use crate::config::LicenseOptions;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+/// Directory name under the platform data directory.
+const APP_DIR: &str = "ironlicensing";
+
+/// Permissions for directories and files readable only by the current user.
+const PRIVATE_DIR_MODE: u32 = 0o700;
+const PRIVATE_FILE_MODE: u32 = 0o600;
+
+/// Permissions for system-wide installs, where services running as other
+/// users need to read shared state.
+const SHARED_DIR_MODE: u32 = 0o755;
+const SHARED_FILE_MODE: u32 = 0o644;
+
+/// Resolved on-disk locations for SDK state.
+///
+/// Machine-level state (the machine ID) lives in the root; everything else
+/// lives in a per-product subdirectory named after the product slug. Sandbox
+/// and custom environments use a subdirectory of the root as their root.
+///
+/// All writes go through this module: files are replaced atomically via
+/// write-and-rename while holding an advisory lock on a `.lock` file beside
+/// them, so concurrent processes never see partial writes or race on
+/// create-if-missing.
+#[derive(Debug, Clone)]
+pub(crate) struct Storage {
+    root: PathBuf,
+    product_slug: String,
+    system_wide: bool,
+    legacy_root: Option<PathBuf>,
+}
+
+impl Storage {
+    pub fn new(options: &LicenseOptions) -> Self {
+        let (mut root, mut legacy_root) = match (&options.storage_dir, options.system_wide) {
+            (Some(dir), _) => (dir.clone(), None),
+            (None, true) => (Self::system_dir(), None),
+            (None, false) => (Self::user_dir(), Self::legacy_dir()),
+        };
+
+        // Non-live environments get their own tree, including the machine ID.
+        // Legacy state predates environments and is always live.
+        if let Some(namespace) = options.environment.storage_namespace() {
+            root.push(namespace);
+            legacy_root = None;
+        }
+
+        Self {
+            root,
+            product_slug: options.product_slug.clone(),
+            system_wide: options.system_wide,
+            legacy_root,
+        }
+    }
+
+    /// Directory for machine-level state shared by all products.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Directory for this product's state.
+    pub fn product_dir(&self) -> PathBuf {
+        self.root.join(&self.product_slug)
+    }
+
+    /// Pre-XDG location (`~/.ironlicensing`) to migrate machine-level state from.
+    pub fn legacy_root(&self) -> Option<&Path> {
+        self.legacy_root.as_deref()
+    }
+
+    /// Write a file readable by whoever may read SDK state: the current user,
+    /// or everyone in a system-wide install.
+    pub fn write(&self, path: &Path, data: &[u8]) -> io::Result<()> {
+        let (dir_mode, file_mode) = self.modes();
+        write_file(path, data, dir_mode, file_mode)
+    }
+
+    /// Append to a file, creating it if it doesn't exist.
+    pub fn append(&self, path: &Path, data: &[u8]) -> io::Result<()> {
+        let (dir_mode, file_mode) = self.modes();
+        with_lock(path, dir_mode, file_mode, || {
+            let mut file = open(path, file_mode, fs::OpenOptions::new().append(true).create(true))?;
+            file.write_all(data)
+        })
+    }
+
+    /// Replace a file's contents with `f` applied to its current contents
+    /// (`None` if it doesn't exist), deleting it if `f` returns `None`.
+    ///
+    /// The read and write happen under the file's lock, so changes other
+    /// processes make in between aren't lost.
+    pub fn update(&self, path: &Path, f: impl FnOnce(Option<Vec<u8>>) -> Option<Vec<u8>>) -> io::Result<()> {
+        let (dir_mode, file_mode) = self.modes();
+        with_lock(path, dir_mode, file_mode, || {
+            let current = match fs::read(path) {
+                Ok(data) => Some(data),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+                Err(e) => return Err(e),
+            };
+            match f(current) {
+                Some(data) => replace(path, &data, file_mode),
+                None => match fs::remove_file(path) {
+                    Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+                    _ => Ok(()),
+                },
+            }
+        })
+    }
+
+    /// Take the exclusive advisory lock on `<path>.lock` without waiting.
+    ///
+    /// Returns `None` if another process holds it. The lock is released when
+    /// the returned file is dropped.
+    pub fn try_lock(&self, path: &Path) -> io::Result<Option<File>> {
+        let (dir_mode, file_mode) = self.modes();
+        if let Some(parent) = path.parent() {
+            create_dir(parent, dir_mode)?;
+        }
+
+        let lock = open_lock(path, file_mode)?;
+        match lock.try_lock() {
+            Ok(()) => Ok(Some(lock)),
+            Err(fs::TryLockError::WouldBlock) => Ok(None),
+            Err(fs::TryLockError::Error(e)) => Err(e),
+        }
+    }
+
+    /// Read a file, creating it with `create` if it doesn't exist.
+    ///
+    /// The check and create happen under the file's lock, so concurrent
+    /// processes agree on a single value. An existing file is read without
+    /// the lock, since writes replace it atomically; this keeps it readable
+    /// by users who can't write beside it, as in a system-wide install.
+    pub fn read_or_create(&self, path: &Path, create: impl FnOnce() -> Vec<u8>) -> io::Result<Vec<u8>> {
+        match fs::read(path) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            read => return read,
+        }
+
+        let (dir_mode, file_mode) = self.modes();
+        with_lock(path, dir_mode, file_mode, || match fs::read(path) {
+            Ok(data) => Ok(data),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                let data = create();
+                replace(path, &data, file_mode)?;
+                Ok(data)
+            }
+            Err(e) => Err(e),
+        })
+    }
+
+    fn modes(&self) -> (u32, u32) {
+        if self.system_wide {
+            (SHARED_DIR_MODE, SHARED_FILE_MODE)
+        } else {
+            (PRIVATE_DIR_MODE, PRIVATE_FILE_MODE)
+        }
+    }
+
+    fn user_dir() -> PathBuf {
+        dirs::data_local_dir()
+            .or_else(dirs::home_dir)
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join(APP_DIR)
+    }
+
+    fn system_dir() -> PathBuf {
+        if cfg!(target_os = "windows") {
+            std::env::var_os("ProgramData")
+                .map(PathBuf::from)
+                .unwrap_or_else(|| PathBuf::from(r"C:\ProgramData"))
+                .join("IronLicensing")
+        } else if cfg!(target_os = "macos") {
+            PathBuf::from("/Library/Application Support/IronLicensing")
+        } else {
+            PathBuf::from("/var/lib").join(APP_DIR)
+        }
+    }
+
+    fn legacy_dir() -> Option<PathBuf> {
+        dirs::home_dir().map(|home| home.join(".ironlicensing"))
+    }
+}
+
+/// Write a file readable only by the current user.
+pub(crate) fn write_private(path: &Path, data: &[u8]) -> io::Result<()> {
+    write_file(path, data, PRIVATE_DIR_MODE, PRIVATE_FILE_MODE)
+}
+
+/// Delete a file under its lock. Deleting a missing file is not an error.
+pub(crate) fn remove(path: &Path) -> io::Result<()> {
+    if !path.exists() {
+        return Ok(());
+    }
+    with_lock(path, PRIVATE_DIR_MODE, PRIVATE_FILE_MODE, || match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    })
+}
+
+fn write_file(path: &Path, data: &[u8], dir_mode: u32, file_mode: u32) -> io::Result<()> {
+    with_lock(path, dir_mode, file_mode, || replace(path, data, file_mode))
+}
+
+/// Run `f` holding an exclusive advisory lock on `<path>.lock`.
+fn with_lock<T>(path: &Path, dir_mode: u32, file_mode: u32, f: impl FnOnce() -> io::Result<T>) -> io::Result<T> {
+    if let Some(parent) = path.parent() {
+        create_dir(parent, dir_mode)?;
+    }
+
+    let lock = open_lock(path, file_mode)?;
+    lock.lock()?;
+    let result = f();
+    let _ = lock.unlock();
+    result
+}
+
+/// Open `<path>.lock`, creating it if needed. A lock file owned by another
+/// user is opened read-only, which advisory locks allow.
+fn open_lock(path: &Path, file_mode: u32) -> io::Result<File> {
+    let lock_path = sibling(path, ".lock");
+    match open(
+        &lock_path,
+        file_mode,
+        fs::OpenOptions::new().write(true).create(true).truncate(false),
+    ) {
+        Err(e) if e.kind() == io::ErrorKind::PermissionDenied => File::open(&lock_path),
+        lock => lock,
+    }
+}
+
+/// Atomically replace `path` by writing a temporary file beside it and
+/// renaming it into place, so readers never see a partial write.
+fn replace(path: &Path, data: &[u8], file_mode: u32) -> io::Result<()> {
+    let tmp = sibling(path, &format!(".{}.tmp", Uuid::new_v4().simple()));
+
+    let result = (|| {
+        let mut file = open(&tmp, file_mode, fs::OpenOptions::new().write(true).create(true).truncate(true))?;
+        file.write_all(data)?;
+        file.sync_all()?;
+        fs::rename(&tmp, path)
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    result
+}
+
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+fn open(path: &Path, file_mode: u32, options: &mut fs::OpenOptions) -> io::Result<File> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(file_mode);
+    }
+    #[cfg(not(unix))]
+    let _ = file_mode;
+
+    options.open(path)
+}
+
+fn create_dir(dir: &Path, mode: u32) -> io::Result<()> {
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(mode);
+    }
+    #[cfg(not(unix))]
+    let _ = mode;
+
+    builder.create(dir)
+}
@@ -0,0 +1,78 @@
+use chrono::{DateTime, Days, NaiveDate, Utc};
+
+/// Parse an RFC 3339 timestamp as returned by the API.
+pub(crate) fn parse_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|dt| dt.with_timezone(&Utc))
+}
+
+/// Parse when something starts: an RFC 3339 timestamp, or a `YYYY-MM-DD`
+/// date meaning the start of that day in UTC.
+pub(crate) fn parse_start(value: &str) -> Option<DateTime<Utc>> {
+    parse_timestamp(value).or_else(|| parse_date(value).map(|date| date.and_time(Default::default()).and_utc()))
+}
+
+/// Parse when something ends: an RFC 3339 timestamp, or a `YYYY-MM-DD`
+/// date meaning the end of that day in UTC.
+pub(crate) fn parse_end(value: &str) -> Option<DateTime<Utc>> {
+    parse_timestamp(value).or_else(|| {
+        let next_day = parse_date(value)?.checked_add_days(Days::new(1))?;
+        Some(next_day.and_time(Default::default()).and_utc())
+    })
+}
+
+/// Whole days from now until the given end timestamp or date, clamped at zero.
+pub(crate) fn days_until(value: &str) -> Option<i64> {
+    parse_end(value).map(|dt| (dt - Utc::now()).num_days().max(0))
+}
+
+fn parse_date(value: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d").ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn utc(value: &str) -> DateTime<Utc> {
+        parse_timestamp(value).unwrap()
+    }
+
+    #[test]
+    fn timestamps_are_converted_to_utc() {
+        assert_eq!(utc("2026-03-01T02:00:00+02:00"), utc("2026-03-01T00:00:00Z"));
+        assert_eq!(parse_start("2026-03-01T12:30:00Z"), Some(utc("2026-03-01T12:30:00Z")));
+        assert_eq!(parse_end("2026-03-01T12:30:00Z"), Some(utc("2026-03-01T12:30:00Z")));
+    }
+
+    #[test]
+    fn dates_cover_the_whole_day() {
+        assert_eq!(parse_start("2026-03-01"), Some(utc("2026-03-01T00:00:00Z")));
+        assert_eq!(parse_end("2026-03-01"), Some(utc("2026-03-02T00:00:00Z")));
+        assert_eq!(parse_end(" 2026-12-31 "), Some(utc("2027-01-01T00:00:00Z")));
+        assert_eq!(parse_end("2028-02-28"), Some(utc("2028-02-29T00:00:00Z")));
+    }
+
+    #[test]
+    fn unparseable_values_are_none() {
+        for value in ["", "tomorrow", "2026-02-30", "2026-03-01 12:00", "01/03/2026"] {
+            assert_eq!(parse_start(value), None, "{}", value);
+            assert_eq!(parse_end(value), None, "{}", value);
+        }
+        assert_eq!(days_until("soon"), None);
+    }
+
+    #[test]
+    fn days_until_counts_whole_days_and_clamps_at_zero() {
+        let in_three_days = (Utc::now() + Duration::days(3) + Duration::hours(1)).to_rfc3339();
+        assert_eq!(days_until(&in_three_days), Some(3));
+
+        let yesterday = (Utc::now() - Duration::days(1)).to_rfc3339();
+        assert_eq!(days_until(&yesterday), Some(0));
+
+        let today = Utc::now().date_naive().to_string();
+        assert_eq!(days_until(&today), Some(0));
+    }
+}
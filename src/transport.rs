@@ -1,20 +1,31 @@
-use crate::config::LicenseOptions;
+use crate::analytics::AnalyticsEvent;
+use crate::config::{Environment, LicenseOptions};
 use crate::error::{LicenseError, Result};
-use crate::types::{CheckoutResult, LicenseResult, ProductTier};
-use reqwest::blocking::Client as HttpClient;
+use crate::key::LicenseKey;
+use crate::storage::Storage;
+use crate::types::{CheckoutResult, CheckoutSession, FailureReason, LicenseResult, ProductTier, TrialStatus};
+use reqwest::blocking::{Client as HttpClient, RequestBuilder};
+use reqwest::header::{ETAG, IF_NONE_MATCH};
+use reqwest::{StatusCode, Url};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
 use uuid::Uuid;
 
+/// File in the storage root holding this machine's ID.
+const MACHINE_ID_FILE: &str = "machine_id";
+
 pub struct Transport {
     base_url: String,
+    sandbox: bool,
     public_key: String,
     product_slug: String,
     debug: bool,
+    pricing_country: Option<String>,
+    pricing_currency: Option<String>,
     http_client: HttpClient,
     machine_id: String,
+    fingerprint: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -23,6 +34,8 @@ struct ValidateRequest {
     license_key: String,
     #[serde(rename = "machineId")]
     machine_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fingerprint: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -34,6 +47,16 @@ struct ActivateRequest {
     #[serde(rename = "machineName")]
     machine_name: String,
     platform: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fingerprint: Option<String>,
+}
+
+#[derive(Serialize)]
+struct TransferRequest {
+    #[serde(rename = "fromMachineId")]
+    from_machine_id: String,
+    #[serde(flatten)]
+    activation: ActivateRequest,
 }
 
 #[derive(Serialize)]
@@ -46,7 +69,33 @@ struct DeactivateRequest {
 
 #[derive(Serialize)]
 struct TrialRequest {
-    email: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    email: Option<String>,
+    #[serde(rename = "machineId")]
+    machine_id: String,
+}
+
+#[derive(Serialize)]
+struct TrialStatusRequest {
+    #[serde(rename = "machineId")]
+    machine_id: String,
+}
+
+#[derive(Serialize)]
+struct TrialExtendRequest {
+    #[serde(rename = "licenseKey", skip_serializing_if = "Option::is_none")]
+    license_key: Option<String>,
+    #[serde(rename = "machineId")]
+    machine_id: String,
+    code: String,
+}
+
+#[derive(Serialize)]
+struct TrialConvertRequest {
+    #[serde(rename = "trialKey", skip_serializing_if = "Option::is_none")]
+    trial_key: Option<String>,
+    #[serde(rename = "licenseKey")]
+    license_key: String,
     #[serde(rename = "machineId")]
     machine_id: String,
 }
@@ -56,6 +105,37 @@ struct CheckoutRequest {
     #[serde(rename = "tierId")]
     tier_id: String,
     email: String,
+    #[serde(rename = "redirectUrl", skip_serializing_if = "Option::is_none")]
+    redirect_url: Option<String>,
+}
+
+#[derive(Serialize)]
+struct UpgradeRequest {
+    #[serde(rename = "licenseKey")]
+    license_key: String,
+    #[serde(rename = "tierId")]
+    tier_id: String,
+    #[serde(rename = "machineId")]
+    machine_id: String,
+}
+
+#[derive(Serialize)]
+struct PortalRequest {
+    #[serde(rename = "licenseKey")]
+    license_key: String,
+}
+
+#[derive(Serialize)]
+struct AnalyticsRequest<'a> {
+    #[serde(rename = "machineId")]
+    machine_id: &'a str,
+    platform: &'a str,
+    events: &'a [AnalyticsEvent],
+}
+
+#[derive(Deserialize)]
+struct PortalResponse {
+    url: String,
 }
 
 #[derive(Deserialize)]
@@ -63,27 +143,47 @@ struct TiersResponse {
     tiers: Vec<ProductTier>,
 }
 
+/// Outcome of a conditional tiers request.
+pub enum TiersFetch {
+    NotModified,
+    Modified {
+        tiers: Vec<ProductTier>,
+        etag: Option<String>,
+    },
+}
+
+/// Outcome of sending a batch of analytics events.
+pub enum EventsDelivery {
+    Sent,
+    /// The server refused the batch and retrying it won't help.
+    Rejected,
+}
+
 #[derive(Deserialize)]
 struct ErrorResponse {
     error: String,
 }
 
 impl Transport {
-    pub fn new(options: &LicenseOptions) -> Self {
+    pub fn new(options: &LicenseOptions, storage: &Storage) -> Self {
         let http_client = HttpClient::builder()
             .timeout(options.http_timeout)
             .build()
             .unwrap_or_default();
 
-        let machine_id = Self::get_or_create_machine_id();
+        let machine_id = Self::get_or_create_machine_id(storage);
 
         Self {
             base_url: options.api_base_url.clone(),
+            sandbox: options.environment == Environment::Sandbox,
             public_key: options.public_key.clone(),
             product_slug: options.product_slug.clone(),
             debug: options.debug,
+            pricing_country: options.pricing_country.clone(),
+            pricing_currency: options.pricing_currency.clone(),
             http_client,
             machine_id,
+            fingerprint: crate::fingerprint::hardware_fingerprint(),
         }
     }
 
@@ -93,41 +193,44 @@ impl Transport {
         }
     }
 
-    fn get_machine_id_path() -> PathBuf {
-        dirs::home_dir()
-            .unwrap_or_else(|| PathBuf::from("."))
-            .join(".ironlicensing")
-            .join("machine_id")
-    }
-
-    fn get_or_create_machine_id() -> String {
-        let id_path = Self::get_machine_id_path();
-
-        if let Ok(id) = fs::read_to_string(&id_path) {
-            return id.trim().to_string();
-        }
-
-        let id = Uuid::new_v4().to_string();
+    fn get_or_create_machine_id(storage: &Storage) -> String {
+        let id_path = storage.root().join(MACHINE_ID_FILE);
+
+        let create = || {
+            // Keep the ID from the pre-XDG location so existing activations
+            // aren't orphaned.
+            storage
+                .legacy_root()
+                .and_then(|dir| fs::read_to_string(dir.join(MACHINE_ID_FILE)).ok())
+                .map(|id| id.trim().to_string())
+                .filter(|id| !id.is_empty())
+                .unwrap_or_else(|| Uuid::new_v4().to_string())
+        };
 
-        if let Some(parent) = id_path.parent() {
-            let _ = fs::create_dir_all(parent);
+        match storage.read_or_create(&id_path, || create().into_bytes()) {
+            Ok(id) => String::from_utf8_lossy(&id).trim().to_string(),
+            // Missing and can't be created: fall back to an ID for this
+            // process only.
+            Err(_) => create(),
         }
-        let _ = fs::write(&id_path, &id);
-
-        id
     }
 
     pub fn machine_id(&self) -> &str {
         &self.machine_id
     }
 
-    fn get_hostname() -> String {
+    /// Hash of this machine's hardware signals, sent with activations.
+    pub fn fingerprint(&self) -> Option<&str> {
+        self.fingerprint.as_deref()
+    }
+
+    pub fn get_hostname() -> String {
         hostname::get()
             .map(|h| h.to_string_lossy().to_string())
             .unwrap_or_else(|_| "unknown".to_string())
     }
 
-    fn get_platform() -> &'static str {
+    pub fn get_platform() -> &'static str {
         if cfg!(target_os = "windows") {
             "windows"
         } else if cfg!(target_os = "macos") {
@@ -139,34 +242,47 @@ impl Transport {
         }
     }
 
-    pub fn validate(&self, license_key: &str) -> LicenseResult {
-        let preview = &license_key[..license_key.len().min(10)];
-        self.log(&format!("Validating: {}...", preview));
+    pub fn validate(&self, license_key: &LicenseKey) -> LicenseResult {
+        self.log(&format!("Validating: {}", license_key.redacted()));
 
         let request = ValidateRequest {
             license_key: license_key.to_string(),
             machine_id: self.machine_id.clone(),
+            fingerprint: self.fingerprint.clone(),
         };
 
         self.post("/api/v1/validate", &request)
     }
 
-    pub fn activate(&self, license_key: &str, machine_name: Option<&str>) -> LicenseResult {
-        let preview = &license_key[..license_key.len().min(10)];
-        self.log(&format!("Activating: {}...", preview));
+    pub fn activate(&self, license_key: &LicenseKey, machine_name: Option<&str>) -> LicenseResult {
+        self.log(&format!("Activating: {}", license_key.redacted()));
+        self.post("/api/v1/activate", &self.activate_request(license_key, machine_name))
+    }
+
+    /// Replace the activation for `from_machine_id` with one for this machine.
+    pub fn transfer_activation(&self, license_key: &LicenseKey, from_machine_id: &str) -> LicenseResult {
+        self.log(&format!("Transferring activation: {}", license_key.redacted()));
+
+        let request = TransferRequest {
+            from_machine_id: from_machine_id.to_string(),
+            activation: self.activate_request(license_key, None),
+        };
+
+        self.post("/api/v1/activations/transfer", &request)
+    }
 
+    fn activate_request(&self, license_key: &LicenseKey, machine_name: Option<&str>) -> ActivateRequest {
         let machine_name = machine_name
             .map(String::from)
             .unwrap_or_else(Self::get_hostname);
 
-        let request = ActivateRequest {
+        ActivateRequest {
             license_key: license_key.to_string(),
             machine_id: self.machine_id.clone(),
             machine_name,
             platform: Self::get_platform().to_string(),
-        };
-
-        self.post("/api/v1/activate", &request)
+            fingerprint: self.fingerprint.clone(),
+        }
     }
 
     pub fn deactivate(&self, license_key: &str) -> bool {
@@ -191,51 +307,199 @@ impl Transport {
         }
     }
 
-    pub fn start_trial(&self, email: &str) -> LicenseResult {
-        self.log(&format!("Starting trial for: {}", email));
+    pub fn start_trial(&self, email: Option<&str>) -> LicenseResult {
+        match email {
+            Some(email) => self.log(&format!("Starting trial for: {}", email)),
+            None => self.log("Starting anonymous trial"),
+        }
 
         let request = TrialRequest {
-            email: email.to_string(),
+            email: email.map(String::from),
             machine_id: self.machine_id.clone(),
         };
 
         self.post("/api/v1/trial", &request)
     }
 
-    pub fn get_tiers(&self) -> Vec<ProductTier> {
+    pub fn trial_status(&self) -> Result<TrialStatus> {
+        self.log("Fetching trial status");
+
+        let request = TrialStatusRequest {
+            machine_id: self.machine_id.clone(),
+        };
+
+        self.request("/api/v1/trial/status", &request)
+    }
+
+    pub fn extend_trial(&self, license_key: Option<&str>, code: &str) -> LicenseResult {
+        self.log("Extending trial");
+
+        let request = TrialExtendRequest {
+            license_key: license_key.map(String::from),
+            machine_id: self.machine_id.clone(),
+            code: code.to_string(),
+        };
+
+        self.post("/api/v1/trial/extend", &request)
+    }
+
+    pub fn convert_trial(&self, trial_key: Option<&str>, license_key: &LicenseKey) -> LicenseResult {
+        self.log("Converting trial");
+
+        let request = TrialConvertRequest {
+            trial_key: trial_key.map(String::from),
+            license_key: license_key.to_string(),
+            machine_id: self.machine_id.clone(),
+        };
+
+        self.post("/api/v1/trial/convert", &request)
+    }
+
+    pub fn fetch_tiers(&self, etag: Option<&str>) -> Result<TiersFetch> {
         self.log("Fetching product tiers");
 
-        match self
+        let mut query = Vec::new();
+        if let Some(country) = &self.pricing_country {
+            query.push(("country", country));
+        }
+        if let Some(currency) = &self.pricing_currency {
+            query.push(("currency", currency));
+        }
+
+        let mut builder = self
             .http_client
             .get(format!("{}/api/v1/tiers", self.base_url))
+            .query(&query)
             .header("Content-Type", "application/json")
             .header("X-Public-Key", &self.public_key)
-            .header("X-Product-Slug", &self.product_slug)
-            .send()
-        {
-            Ok(resp) if resp.status().is_success() => resp
-                .json::<TiersResponse>()
-                .map(|r| r.tiers)
-                .unwrap_or_default(),
-            _ => vec![],
+            .header("X-Product-Slug", &self.product_slug);
+        if let Some(etag) = etag {
+            builder = builder.header(IF_NONE_MATCH, etag);
+        }
+
+        let resp = builder.send()?;
+        let status = resp.status();
+
+        if status == StatusCode::NOT_MODIFIED {
+            return Ok(TiersFetch::NotModified);
+        }
+
+        let etag = resp
+            .headers()
+            .get(ETAG)
+            .and_then(|v| v.to_str().ok())
+            .map(String::from);
+        let body = resp.text()?;
+
+        if status.is_success() {
+            let tiers = serde_json::from_str::<TiersResponse>(&body)?.tiers;
+            Ok(TiersFetch::Modified { tiers, etag })
+        } else {
+            let error = serde_json::from_str::<ErrorResponse>(&body)
+                .map(|e| e.error)
+                .unwrap_or_else(|_| "Failed to fetch tiers".to_string());
+            Err(status_error(status, error))
         }
     }
 
-    pub fn start_checkout(&self, tier_id: &str, email: &str) -> CheckoutResult {
+    pub fn start_checkout(&self, tier_id: &str, email: &str, redirect_url: Option<&str>) -> CheckoutResult {
         self.log(&format!("Starting checkout for tier: {}", tier_id));
 
         let request = CheckoutRequest {
             tier_id: tier_id.to_string(),
             email: email.to_string(),
+            redirect_url: redirect_url.map(String::from),
         };
 
-        match self
+        self.post_checkout("/api/v1/checkout", &request)
+    }
+
+    pub fn start_upgrade(&self, license_key: &str, tier_id: &str) -> CheckoutResult {
+        self.log(&format!("Starting upgrade to tier: {}", tier_id));
+
+        let request = UpgradeRequest {
+            license_key: license_key.to_string(),
+            tier_id: tier_id.to_string(),
+            machine_id: self.machine_id.clone(),
+        };
+
+        self.post_checkout("/api/v1/checkout/upgrade", &request)
+    }
+
+    pub fn customer_portal_url(&self, license_key: &str) -> Result<String> {
+        self.log("Requesting customer portal URL");
+
+        let request = PortalRequest {
+            license_key: license_key.to_string(),
+        };
+
+        self.request::<_, PortalResponse>("/api/v1/portal", &request)
+            .map(|r| r.url)
+    }
+
+    pub fn checkout_status(&self, session_id: &str) -> Result<CheckoutSession> {
+        self.log(&format!("Polling checkout session: {}", session_id));
+
+        // The session ID is caller-supplied; encode it as a single path segment.
+        // Dot segments would still be resolved, so refuse them outright.
+        if matches!(session_id, "" | "." | "..") {
+            return Err(LicenseError::InvalidSessionId(session_id.to_string()));
+        }
+        let mut url = Url::parse(&format!("{}/api/v1/checkout", self.base_url))
+            .map_err(|e| LicenseError::InvalidApiBaseUrl(e.to_string()))?;
+        url.path_segments_mut()
+            .map_err(|_| LicenseError::InvalidApiBaseUrl(self.base_url.clone()))?
+            .push(session_id);
+
+        self.send(self.http_client.get(url))
+    }
+
+    /// Errors are retryable failures: network errors, 5xx, 408 and 429.
+    pub fn send_events(&self, events: &[AnalyticsEvent]) -> Result<EventsDelivery> {
+        self.log(&format!("Sending {} analytics events", events.len()));
+
+        let request = AnalyticsRequest {
+            machine_id: &self.machine_id,
+            platform: Self::get_platform(),
+            events,
+        };
+
+        let resp = self
             .http_client
-            .post(format!("{}/api/v1/checkout", self.base_url))
+            .post(format!("{}/api/v1/analytics/events", self.base_url))
             .header("Content-Type", "application/json")
             .header("X-Public-Key", &self.public_key)
             .header("X-Product-Slug", &self.product_slug)
             .json(&request)
+            .send()?;
+
+        let status = resp.status();
+        if status.is_success() {
+            return Ok(EventsDelivery::Sent);
+        }
+
+        let error = serde_json::from_str::<ErrorResponse>(&resp.text().unwrap_or_default())
+            .map(|e| e.error)
+            .unwrap_or_else(|_| "Request failed".to_string());
+        let retryable = status.is_server_error()
+            || status == StatusCode::REQUEST_TIMEOUT
+            || status == StatusCode::TOO_MANY_REQUESTS;
+        if retryable {
+            Err(status_error(status, error))
+        } else {
+            self.log(&format!("Analytics events rejected, dropping batch: {}", error));
+            Ok(EventsDelivery::Rejected)
+        }
+    }
+
+    fn post_checkout<T: Serialize>(&self, path: &str, body: &T) -> CheckoutResult {
+        match self
+            .http_client
+            .post(format!("{}{}", self.base_url, path))
+            .header("Content-Type", "application/json")
+            .header("X-Public-Key", &self.public_key)
+            .header("X-Product-Slug", &self.product_slug)
+            .json(body)
             .send()
         {
             Ok(resp) => {
@@ -276,15 +540,70 @@ impl Transport {
                 let body = resp.text().unwrap_or_default();
 
                 if status.is_success() {
-                    serde_json::from_str(&body).unwrap_or_else(|e| LicenseResult::failure(e.to_string()))
+                    let mut result: LicenseResult = serde_json::from_str(&body)
+                        .unwrap_or_else(|e| LicenseResult::rejected(FailureReason::Unreachable, e.to_string()));
+                    if let Some(license) = result.license.as_mut() {
+                        license.sandbox |= self.sandbox;
+                    }
+                    if !result.valid && result.reason.is_none() {
+                        result.reason = Some(FailureReason::Rejected);
+                    }
+                    result
                 } else {
                     let error = serde_json::from_str::<ErrorResponse>(&body)
                         .map(|e| e.error)
                         .unwrap_or_else(|_| "Request failed".to_string());
-                    LicenseResult::failure(error)
+                    let reason = if status.is_server_error() {
+                        FailureReason::Unreachable
+                    } else {
+                        FailureReason::Rejected
+                    };
+                    LicenseResult::rejected(reason, error)
                 }
             }
-            Err(e) => LicenseResult::failure(e.to_string()),
+            Err(e) => LicenseResult::rejected(FailureReason::Unreachable, e.to_string()),
+        }
+    }
+
+    fn request<B: Serialize, T: DeserializeOwned>(&self, path: &str, body: &B) -> Result<T> {
+        let builder = self
+            .http_client
+            .post(format!("{}{}", self.base_url, path))
+            .json(body);
+
+        self.send(builder)
+    }
+
+    fn send<T: DeserializeOwned>(&self, builder: RequestBuilder) -> Result<T> {
+        let resp = builder
+            .header("Content-Type", "application/json")
+            .header("X-Public-Key", &self.public_key)
+            .header("X-Product-Slug", &self.product_slug)
+            .send()?;
+
+        let status = resp.status();
+        let body = resp.text()?;
+
+        if status.is_success() {
+            Ok(serde_json::from_str(&body)?)
+        } else {
+            let error = serde_json::from_str::<ErrorResponse>(&body)
+                .map(|e| e.error)
+                .unwrap_or_else(|_| "Request failed".to_string());
+            Err(status_error(status, error))
+        }
+    }
+}
+
+/// The error for a failed response: [`LicenseError::Server`] for 5xx
+/// statuses, which may succeed on retry, and [`LicenseError::Api`] otherwise.
+fn status_error(status: StatusCode, message: String) -> LicenseError {
+    if status.is_server_error() {
+        LicenseError::Server {
+            status: status.as_u16(),
+            message,
         }
+    } else {
+        LicenseError::Api(message)
     }
 }
@@ -1,8 +1,13 @@
+use crate::hierarchy::FeatureHierarchy;
+use crate::money::{BillingPeriod, Money, RegionalPrice};
+use crate::version::BuildVersion;
+use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::cmp::Ordering;
 
 /// License status representing the current state of a license.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum LicenseStatus {
     Valid,
@@ -12,44 +17,99 @@ pub enum LicenseStatus {
     Invalid,
     Trial,
     TrialExpired,
+    #[default]
     NotActivated,
     #[serde(other)]
     Unknown,
 }
 
-impl Default for LicenseStatus {
-    fn default() -> Self {
-        Self::NotActivated
-    }
-}
-
 /// License type.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum LicenseType {
+    #[default]
     Perpetual,
     Subscription,
     Trial,
 }
 
-impl Default for LicenseType {
-    fn default() -> Self {
-        Self::Perpetual
-    }
+/// Where a feature was granted from.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum FeatureSource {
+    /// The primary license.
+    #[default]
+    License,
+    /// An add-on license key activated alongside the primary license.
+    AddOn {
+        #[serde(rename = "licenseKey")]
+        license_key: String,
+    },
 }
 
 /// A feature included in a license.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct Feature {
     pub key: String,
     pub name: String,
     pub enabled: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
+    /// Keys of features this feature enables.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub implies: Vec<String>,
+    /// Start of the feature's validity window, if it has one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub starts_at: Option<String>,
+    /// End of the feature's validity window, if it has one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<String>,
+    #[serde(default)]
+    pub source: FeatureSource,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub metadata: Option<HashMap<String, serde_json::Value>>,
 }
 
+impl Feature {
+    /// An enabled feature with no details, named after its key.
+    pub(crate) fn named(key: &str) -> Self {
+        Self {
+            key: key.to_string(),
+            name: key.to_string(),
+            enabled: true,
+            description: None,
+            implies: Vec::new(),
+            starts_at: None,
+            expires_at: None,
+            source: FeatureSource::default(),
+            metadata: None,
+        }
+    }
+
+    /// Check if the feature is enabled and inside its validity window.
+    /// Bounds may be RFC 3339 timestamps or `YYYY-MM-DD` dates, which cover
+    /// the whole day in UTC. A bound that can't be parsed makes the feature
+    /// inactive.
+    pub fn is_active(&self) -> bool {
+        let now = Utc::now();
+        let started = match self.starts_at.as_deref() {
+            Some(start) => crate::time::parse_start(start).is_some_and(|start| start <= now),
+            None => true,
+        };
+        let expired = match self.expires_at.as_deref() {
+            Some(end) => crate::time::parse_end(end).is_none_or(|end| end <= now),
+            None => false,
+        };
+        self.enabled && started && !expired
+    }
+
+    /// Whole days until the feature expires, or `None` if it doesn't.
+    pub fn days_remaining(&self) -> Option<i64> {
+        self.expires_at.as_deref().and_then(crate::time::days_until)
+    }
+}
+
 /// License information.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -65,6 +125,8 @@ pub struct License {
     pub name: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub company: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tier_id: Option<String>,
     #[serde(default)]
     pub features: Vec<Feature>,
     #[serde(default)]
@@ -78,21 +140,189 @@ pub struct License {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub last_validated_at: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub subscription: Option<Subscription>,
+    /// Last day of the updates entitlement; builds released after it are not covered.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updates_until: Option<String>,
+    /// Highest version covered by the updates entitlement.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_version: Option<String>,
+    /// Issued by the sandbox environment.
+    #[serde(default)]
+    pub sandbox: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub metadata: Option<HashMap<String, serde_json::Value>>,
 }
 
 impl License {
-    /// Check if a feature is enabled.
+    /// Check if a feature is enabled, directly or through a server-sent
+    /// implication. Implications declared in `LicenseOptions::feature_hierarchy`
+    /// aren't known to the license; use `LicenseClient::has_feature` for those.
     pub fn has_feature(&self, feature_key: &str) -> bool {
-        self.features
+        self.effective_features(&FeatureHierarchy::default())
             .iter()
-            .any(|f| f.key == feature_key && f.enabled)
+            .any(|f| f.key == feature_key)
+    }
+
+    /// Active features plus everything they imply through `hierarchy`.
+    pub fn effective_features(&self, hierarchy: &FeatureHierarchy) -> Vec<Feature> {
+        hierarchy.expand(&self.features)
     }
 
     /// Get a feature by key.
     pub fn get_feature(&self, feature_key: &str) -> Option<&Feature> {
         self.features.iter().find(|f| f.key == feature_key)
     }
+
+    /// Check if the subscription payment has failed and the license is
+    /// running on its payment grace period.
+    pub fn is_in_dunning(&self) -> bool {
+        self.subscription
+            .as_ref()
+            .map(Subscription::is_in_dunning)
+            .unwrap_or(false)
+    }
+
+    /// Where the subscription stands after a failed payment.
+    pub fn payment_state(&self) -> PaymentState {
+        self.subscription
+            .as_ref()
+            .map(Subscription::payment_state)
+            .unwrap_or_default()
+    }
+
+    /// When the subscription will next renew, if it is set to renew.
+    pub fn renews_at(&self) -> Option<&str> {
+        self.subscription.as_ref().and_then(Subscription::renews_at)
+    }
+
+    /// Check if a build, given as a release date or version, is covered by
+    /// the updates entitlement. Unparseable builds are not allowed.
+    /// See [`is_build_allowed`](Self::is_build_allowed).
+    pub fn is_version_allowed(&self, build_date_or_semver: &str) -> bool {
+        BuildVersion::parse(build_date_or_semver)
+            .map(|build| self.is_build_allowed(&build))
+            .unwrap_or(false)
+    }
+
+    /// Check if a build is covered by the updates entitlement.
+    ///
+    /// Release dates are checked against `updates_until`, an RFC 3339
+    /// timestamp or a `YYYY-MM-DD` date covering that whole day in UTC, and
+    /// versions against `max_version`. A build is allowed when the license
+    /// sets no limit of that kind, so licenses without an updates window run
+    /// any build. A limit that can't be parsed allows no builds.
+    pub fn is_build_allowed(&self, build: &BuildVersion) -> bool {
+        match build {
+            BuildVersion::Date(date) => match self.updates_until.as_deref() {
+                Some(until) => crate::time::parse_end(until)
+                    .is_some_and(|until| date.and_time(Default::default()).and_utc() < until),
+                None => true,
+            },
+            BuildVersion::Semver(version) => match self.max_version.as_deref() {
+                Some(max) => semver::Version::parse(max.trim().trim_start_matches('v')).is_ok_and(|max| *version <= max),
+                None => true,
+            },
+        }
+    }
+
+    /// Whole days left in the updates entitlement.
+    pub fn updates_days_remaining(&self) -> Option<i64> {
+        self.updates_until.as_deref().and_then(crate::time::days_until)
+    }
+}
+
+/// Billing state of a subscription.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SubscriptionStatus {
+    #[default]
+    Active,
+    Trialing,
+    PastDue,
+    Unpaid,
+    Canceled,
+    #[serde(other)]
+    Unknown,
+}
+
+/// Where a subscription stands after a failed payment.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PaymentState {
+    /// No payment has failed.
+    #[default]
+    Current,
+    /// A payment failed and the payment grace period is still running.
+    InGrace,
+    /// A payment failed and the payment grace period has ended.
+    GraceExpired,
+}
+
+/// Subscription details for `LicenseType::Subscription` licenses.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Subscription {
+    pub status: SubscriptionStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub current_period_end: Option<String>,
+    #[serde(default)]
+    pub cancel_at_period_end: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payment_grace_ends_at: Option<String>,
+}
+
+impl Subscription {
+    /// Whether a payment has failed and, if so, whether the grace period
+    /// is still running. Without a grace end date the server's status is
+    /// trusted; a date that can't be parsed counts as ended.
+    pub fn payment_state(&self) -> PaymentState {
+        if !matches!(self.status, SubscriptionStatus::PastDue | SubscriptionStatus::Unpaid) {
+            return PaymentState::Current;
+        }
+        let grace_over = self
+            .payment_grace_ends_at
+            .as_deref()
+            .is_some_and(|end| crate::time::parse_end(end).is_none_or(|end| end <= Utc::now()));
+        if grace_over {
+            PaymentState::GraceExpired
+        } else {
+            PaymentState::InGrace
+        }
+    }
+
+    /// Check if a payment has failed and the subscription is in its grace period.
+    pub fn is_in_dunning(&self) -> bool {
+        self.payment_state() == PaymentState::InGrace
+    }
+
+    /// Check if a payment has failed and the grace period has ended.
+    pub fn is_grace_expired(&self) -> bool {
+        self.payment_state() == PaymentState::GraceExpired
+    }
+
+    /// When the subscription will next renew, or `None` if it won't.
+    pub fn renews_at(&self) -> Option<&str> {
+        match self.status {
+            SubscriptionStatus::Active | SubscriptionStatus::Trialing if !self.cancel_at_period_end => {
+                self.current_period_end.as_deref()
+            }
+            _ => None,
+        }
+    }
+
+    /// Whole days until the current period ends.
+    pub fn days_until_period_end(&self) -> Option<i64> {
+        self.current_period_end.as_deref().and_then(crate::time::days_until)
+    }
+
+    /// Whole days left before a past-due subscription is locked out.
+    pub fn grace_days_remaining(&self) -> Option<i64> {
+        if !self.is_in_dunning() {
+            return None;
+        }
+        self.payment_grace_ends_at.as_deref().and_then(crate::time::days_until)
+    }
 }
 
 /// An activation of a license on a machine.
@@ -109,6 +339,77 @@ pub struct Activation {
     pub activated_at: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub last_seen_at: Option<String>,
+    /// Hash of the machine's hardware signals at activation.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fingerprint: Option<String>,
+}
+
+/// How prominently a server message should be shown.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MessageSeverity {
+    #[default]
+    Info,
+    Warning,
+    Critical,
+    #[serde(other)]
+    Unknown,
+}
+
+/// A notice from the IronLicensing dashboard, such as an upcoming expiry
+/// or a new release, to show alongside the license.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerMessage {
+    pub id: String,
+    #[serde(default)]
+    pub severity: MessageSeverity,
+    pub text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub action_url: Option<String>,
+    /// Key a dismissal is remembered under, so a recurring notice stays
+    /// dismissed across messages. Defaults to the message ID.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dismiss_key: Option<String>,
+}
+
+impl ServerMessage {
+    /// The key a dismissal of this message is recorded under.
+    pub(crate) fn dismissal_key(&self) -> &str {
+        self.dismiss_key.as_deref().unwrap_or(&self.id)
+    }
+}
+
+/// Why a license result isn't valid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureReason {
+    /// The license key is malformed; no request was made.
+    InvalidKey,
+    /// The server couldn't be reached or gave no usable answer, e.g. a
+    /// network error or a 5xx response.
+    Unreachable,
+    /// The server answered that the license isn't valid; see `error`.
+    Rejected,
+    /// The license is valid but not activated on this machine.
+    NotActivatedOnThisMachine,
+    /// A sandbox license was refused by `reject_sandbox_licenses`.
+    SandboxLicenseRejected,
+    /// `transfer_activation` was called without a transferable activation.
+    NoTransferableActivation,
+}
+
+impl FailureReason {
+    /// Error code used in `LicenseResult::error` for failures detected locally.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::InvalidKey => "invalid_key",
+            Self::Unreachable => "unreachable",
+            Self::Rejected => "rejected",
+            Self::NotActivatedOnThisMachine => "not_activated_on_this_machine",
+            Self::SandboxLicenseRejected => "sandbox_license_rejected",
+            Self::NoTransferableActivation => "no_transferable_activation",
+        }
+    }
 }
 
 /// Result of a license validation or activation.
@@ -121,6 +422,20 @@ pub struct LicenseResult {
     pub activations: Option<Vec<Activation>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<String>,
+    /// Why the result isn't valid, when known. Set by the SDK, not the server.
+    #[serde(skip)]
+    pub reason: Option<FailureReason>,
+    /// Whether the license is activated on the requesting machine, if the
+    /// server says.
+    #[serde(rename = "activatedOnMachine", skip_serializing_if = "Option::is_none")]
+    pub activated_on_machine: Option<bool>,
+    /// An activation from this machine under a previous machine ID, which
+    /// `LicenseClient::transfer_activation` can move to the current one.
+    #[serde(rename = "transferableActivation", skip_serializing_if = "Option::is_none")]
+    pub transferable_activation: Option<Activation>,
+    /// Notices the server attached to this license.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub messages: Vec<ServerMessage>,
     #[serde(default)]
     pub cached: bool,
 }
@@ -132,6 +447,10 @@ impl LicenseResult {
             license: Some(license),
             activations: None,
             error: None,
+            reason: None,
+            activated_on_machine: None,
+            transferable_activation: None,
+            messages: Vec::new(),
             cached: false,
         }
     }
@@ -142,9 +461,51 @@ impl LicenseResult {
             license: None,
             activations: None,
             error: Some(error.into()),
+            reason: None,
+            activated_on_machine: None,
+            transferable_activation: None,
+            messages: Vec::new(),
             cached: false,
         }
     }
+
+    /// A failure with a reason and an error message.
+    pub fn rejected(reason: FailureReason, error: impl Into<String>) -> Self {
+        Self {
+            reason: Some(reason),
+            ..Self::failure(error)
+        }
+    }
+
+    /// A failure detected locally, with the reason's code as the error.
+    pub(crate) fn local_failure(reason: FailureReason) -> Self {
+        Self::rejected(reason, reason.as_str())
+    }
+
+    /// Check if the license is valid but not activated on this machine,
+    /// e.g. a key copied from another user. The license is still included.
+    pub fn is_not_activated_on_this_machine(&self) -> bool {
+        self.reason == Some(FailureReason::NotActivatedOnThisMachine)
+    }
+
+    /// An activation with this machine's hardware `fingerprint` but another
+    /// machine ID, meaning the machine ID changed since activation.
+    pub(crate) fn drifted_activation(&self, machine_id: &str, fingerprint: &str) -> Option<&Activation> {
+        self.activations
+            .as_ref()?
+            .iter()
+            .find(|a| a.machine_id != machine_id && a.fingerprint.as_deref() == Some(fingerprint))
+    }
+
+    /// Whether `machine_id` is bound to the license, from the server's flag
+    /// or else the activation list. `None` if the server sent neither.
+    pub(crate) fn is_bound_to(&self, machine_id: &str) -> Option<bool> {
+        self.activated_on_machine.or_else(|| {
+            self.activations
+                .as_ref()
+                .map(|activations| activations.iter().any(|a| a.machine_id == machine_id))
+        })
+    }
 }
 
 /// Result of starting a checkout.
@@ -180,19 +541,399 @@ impl CheckoutResult {
     }
 }
 
-/// A product tier available for purchase.
+/// State of a checkout session.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckoutStatus {
+    #[default]
+    Pending,
+    Completed,
+    Expired,
+    Canceled,
+    Failed,
+    #[serde(other)]
+    Unknown,
+}
+
+/// Status of a checkout session started with `start_purchase`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
+pub struct CheckoutSession {
+    pub session_id: String,
+    pub status: CheckoutStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub license_key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl CheckoutSession {
+    /// Check if the session is still waiting for payment.
+    pub fn is_pending(&self) -> bool {
+        self.status == CheckoutStatus::Pending
+    }
+
+    /// Check if the purchase completed.
+    pub fn is_completed(&self) -> bool {
+        self.status == CheckoutStatus::Completed
+    }
+}
+
+/// A product tier available for purchase.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "ProductTierWire", into = "ProductTierWire")]
 pub struct ProductTier {
     pub id: String,
     pub slug: String,
     pub name: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
-    pub price: f64,
-    pub currency: String,
+    pub price: Money,
+    pub billing_period: BillingPeriod,
+    /// Per-country prices, when the server provides a price list.
+    pub regional_prices: Vec<RegionalPrice>,
+    pub features: Vec<Feature>,
+}
+
+impl ProductTier {
+    /// Compare this tier's price with another's. Recurring prices are
+    /// compared per year. Returns `None` for different currencies, or for a
+    /// one-time price against a recurring one.
+    pub fn compare_price(&self, other: &ProductTier) -> Option<Ordering> {
+        if self.price.currency() != other.price.currency() {
+            return None;
+        }
+
+        let (amount, other_amount) = (self.price.amount_minor(), other.price.amount_minor());
+        match (self.billing_period.periods_per_year(), other.billing_period.periods_per_year()) {
+            (Some(periods), Some(other_periods)) => Some((amount * periods).cmp(&(other_amount * other_periods))),
+            _ if self.billing_period == other.billing_period => Some(amount.cmp(&other_amount)),
+            _ => None,
+        }
+    }
+
+    /// Features of this tier plus everything they imply through `hierarchy`.
+    pub fn effective_features(&self, hierarchy: &FeatureHierarchy) -> Vec<Feature> {
+        hierarchy.expand(&self.features)
+    }
+
+    /// Get the price for a country, falling back to the default price.
+    pub fn price_for_country(&self, country: &str) -> &Money {
+        self.regional_prices
+            .iter()
+            .find(|p| p.country.eq_ignore_ascii_case(country))
+            .map(|p| &p.price)
+            .unwrap_or(&self.price)
+    }
+
+    /// Format the price for a locale such as `"en-US"` or `"de-DE"`.
+    pub fn format_price(&self, locale: &str) -> String {
+        self.price.format(locale)
+    }
+}
+
+/// API representation of a product tier; prices are sent in major units
+/// with an optional exact minor-unit amount.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ProductTierWire {
+    id: String,
+    slug: String,
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    price: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    price_minor: Option<i64>,
+    currency: String,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub billing_period: Option<String>,
+    billing_period: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    regional_prices: Vec<RegionalPrice>,
     #[serde(default)]
-    pub features: Vec<Feature>,
+    features: Vec<Feature>,
+}
+
+impl From<ProductTierWire> for ProductTier {
+    fn from(wire: ProductTierWire) -> Self {
+        let price = match wire.price_minor {
+            Some(minor) => Money::from_minor(minor, wire.currency),
+            None => Money::from_major(wire.price, wire.currency),
+        };
+
+        Self {
+            id: wire.id,
+            slug: wire.slug,
+            name: wire.name,
+            description: wire.description,
+            price,
+            billing_period: BillingPeriod::parse(wire.billing_period.as_deref()),
+            regional_prices: wire.regional_prices,
+            features: wire.features,
+        }
+    }
+}
+
+impl From<ProductTier> for ProductTierWire {
+    fn from(tier: ProductTier) -> Self {
+        Self {
+            id: tier.id,
+            slug: tier.slug,
+            name: tier.name,
+            description: tier.description,
+            price: tier.price.as_major(),
+            price_minor: Some(tier.price.amount_minor()),
+            currency: tier.price.currency().to_string(),
+            billing_period: Some(tier.billing_period.as_str().to_string()),
+            regional_prices: tier.regional_prices,
+            features: tier.features,
+        }
+    }
+}
+
+/// Direction of a move from the current tier to another tier.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TierChange {
+    Upgrade,
+    Downgrade,
+    Lateral,
+    /// Neither price nor features tell which way the move goes, e.g. prices
+    /// in different currencies and features both gained and lost.
+    Unknown,
+}
+
+/// A tier the current license can move to, with the feature difference.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpgradeOption {
+    pub tier: ProductTier,
+    pub change: TierChange,
+    /// Features the tier grants, directly or by implication, that the
+    /// current license doesn't.
+    pub gained_features: Vec<Feature>,
+    /// Features the current license grants, directly or by implication,
+    /// that the tier doesn't.
+    pub lost_features: Vec<Feature>,
+}
+
+impl UpgradeOption {
+    /// Compare a tier against the current license and its tier, if known,
+    /// resolving implied features through `hierarchy`.
+    pub fn compare(
+        tier: ProductTier,
+        license: &License,
+        current_tier: Option<&ProductTier>,
+        hierarchy: &FeatureHierarchy,
+    ) -> Self {
+        // Both sides include implied features, so e.g. a tier whose
+        // `enterprise` implies `sso` lists `sso` as gained.
+        let license_features = license.effective_features(hierarchy);
+        let tier_features = tier.effective_features(hierarchy);
+        let missing_from = |features: &[Feature], others: &[Feature]| -> Vec<Feature> {
+            features
+                .iter()
+                .filter(|f| !others.iter().any(|o| o.key == f.key))
+                .cloned()
+                .collect()
+        };
+
+        let gained_features = missing_from(&tier_features, &license_features);
+        let lost_features = missing_from(&license_features, &tier_features);
+
+        // Prices decide when they're comparable; otherwise the feature difference does.
+        let change = match current_tier.and_then(|current| tier.compare_price(current)) {
+            Some(Ordering::Greater) => TierChange::Upgrade,
+            Some(Ordering::Less) => TierChange::Downgrade,
+            Some(Ordering::Equal) => TierChange::Lateral,
+            None => match (gained_features.is_empty(), lost_features.is_empty()) {
+                (false, true) => TierChange::Upgrade,
+                (true, false) => TierChange::Downgrade,
+                (true, true) => TierChange::Lateral,
+                (false, false) => TierChange::Unknown,
+            },
+        };
+
+        Self {
+            tier,
+            change,
+            gained_features,
+            lost_features,
+        }
+    }
+}
+
+/// State of the trial for this machine.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrialState {
+    #[default]
+    NotStarted,
+    Active,
+    Expired,
+    Converted,
+    #[serde(other)]
+    Unknown,
+}
+
+/// Trial status for this machine.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrialStatus {
+    pub state: TrialState,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub license_key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ends_at: Option<String>,
+    #[serde(default)]
+    pub extensions: u32,
+}
+
+impl TrialStatus {
+    /// Check if the trial is currently active.
+    pub fn is_active(&self) -> bool {
+        self.state == TrialState::Active
+    }
+
+    /// Whole days left in the trial, if it has an end date.
+    pub fn days_remaining(&self) -> Option<i64> {
+        self.ends_at.as_deref().and_then(crate::time::days_until)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn past_due(grace_ends_at: Option<String>) -> Subscription {
+        Subscription {
+            status: SubscriptionStatus::PastDue,
+            payment_grace_ends_at: grace_ends_at,
+            ..Subscription::default()
+        }
+    }
+
+    fn rfc3339(offset: Duration) -> String {
+        (Utc::now() + offset).to_rfc3339()
+    }
+
+    /// A valid perpetual license with `fields` set on top.
+    fn license(fields: serde_json::Value) -> License {
+        let mut license = serde_json::json!({ "id": "1", "key": "K", "status": "valid", "type": "perpetual" });
+        license.as_object_mut().unwrap().extend(fields.as_object().unwrap().clone());
+        serde_json::from_value(license).unwrap()
+    }
+
+    fn build(value: &str) -> BuildVersion {
+        BuildVersion::parse(value).unwrap()
+    }
+
+    #[test]
+    fn payment_state_is_current_unless_a_payment_failed() {
+        let subscription = Subscription {
+            status: SubscriptionStatus::Active,
+            payment_grace_ends_at: Some(rfc3339(-Duration::days(3))),
+            ..Subscription::default()
+        };
+        assert_eq!(subscription.payment_state(), PaymentState::Current);
+        assert!(!subscription.is_in_dunning());
+    }
+
+    #[test]
+    fn payment_state_trusts_the_server_without_a_grace_date() {
+        assert_eq!(past_due(None).payment_state(), PaymentState::InGrace);
+
+        let unpaid = Subscription {
+            status: SubscriptionStatus::Unpaid,
+            ..Subscription::default()
+        };
+        assert_eq!(unpaid.payment_state(), PaymentState::InGrace);
+    }
+
+    #[test]
+    fn payment_state_checks_the_grace_end_date() {
+        let running = past_due(Some(rfc3339(Duration::days(2))));
+        assert_eq!(running.payment_state(), PaymentState::InGrace);
+        assert!(running.is_in_dunning());
+        assert_eq!(running.grace_days_remaining(), Some(1));
+
+        let ended = past_due(Some(rfc3339(-Duration::minutes(1))));
+        assert_eq!(ended.payment_state(), PaymentState::GraceExpired);
+        assert!(ended.is_grace_expired());
+        assert!(!ended.is_in_dunning());
+        assert_eq!(ended.grace_days_remaining(), None);
+    }
+
+    #[test]
+    fn payment_state_treats_a_date_only_grace_end_as_end_of_day() {
+        let today = Utc::now().date_naive().to_string();
+        assert_eq!(past_due(Some(today)).payment_state(), PaymentState::InGrace);
+
+        let yesterday = (Utc::now() - Duration::days(1)).date_naive().to_string();
+        assert_eq!(past_due(Some(yesterday)).payment_state(), PaymentState::GraceExpired);
+    }
+
+    #[test]
+    fn payment_state_fails_closed_on_an_unparseable_grace_date() {
+        let subscription = past_due(Some("next tuesday".to_string()));
+        assert_eq!(subscription.payment_state(), PaymentState::GraceExpired);
+    }
+
+    #[test]
+    fn license_without_subscription_is_current() {
+        assert_eq!(license(serde_json::json!({})).payment_state(), PaymentState::Current);
+    }
+
+    #[test]
+    fn builds_are_allowed_without_limits() {
+        let license = license(serde_json::json!({}));
+        assert!(license.is_build_allowed(&build("2099-01-01")));
+        assert!(license.is_build_allowed(&build("99.0.0")));
+    }
+
+    #[test]
+    fn date_only_updates_until_covers_the_whole_day() {
+        let license = license(serde_json::json!({ "updatesUntil": "2026-03-01" }));
+        assert!(license.is_build_allowed(&build("2026-02-28")));
+        assert!(license.is_build_allowed(&build("2026-03-01")));
+        assert!(!license.is_build_allowed(&build("2026-03-02")));
+    }
+
+    #[test]
+    fn timestamp_updates_until_allows_builds_released_before_it() {
+        let noon = license(serde_json::json!({ "updatesUntil": "2026-03-01T12:00:00Z" }));
+        assert!(noon.is_build_allowed(&build("2026-03-01")));
+        assert!(noon.is_build_allowed(&build("2026-03-01T18:00:00+02:00")));
+        assert!(!noon.is_build_allowed(&build("2026-03-02")));
+
+        let midnight = license(serde_json::json!({ "updatesUntil": "2026-03-01T00:00:00Z" }));
+        assert!(!midnight.is_build_allowed(&build("2026-03-01")));
+        assert!(midnight.is_build_allowed(&build("2026-02-28")));
+    }
+
+    #[test]
+    fn max_version_is_inclusive() {
+        let license = license(serde_json::json!({ "maxVersion": "v2.4.0" }));
+        assert!(license.is_build_allowed(&build("2.4.0")));
+        assert!(license.is_build_allowed(&build("v1.9.9")));
+        assert!(!license.is_build_allowed(&build("2.4.1")));
+        assert!(!license.is_build_allowed(&build("3.0.0-beta.1")));
+    }
+
+    #[test]
+    fn unparseable_limits_allow_no_builds() {
+        let license = license(serde_json::json!({ "updatesUntil": "someday", "maxVersion": "2.x" }));
+        assert!(!license.is_build_allowed(&build("2000-01-01")));
+        assert!(!license.is_build_allowed(&build("0.0.1")));
+    }
+
+    #[test]
+    fn unparseable_builds_are_not_allowed() {
+        let license = license(serde_json::json!({}));
+        assert!(!license.is_version_allowed("latest"));
+        assert!(!license.is_version_allowed("2026-13-01"));
+        assert!(license.is_version_allowed(" v1.2.3 "));
+    }
 }
@@ -0,0 +1,50 @@
+use crate::error::{LicenseError, Result};
+use chrono::{DateTime, NaiveDate};
+use std::fmt;
+use std::str::FromStr;
+
+/// A build of the application, identified by release date or version,
+/// checked against a license's updates entitlement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BuildVersion {
+    /// The date the build was released.
+    Date(NaiveDate),
+    /// The build's semantic version.
+    Semver(semver::Version),
+}
+
+impl BuildVersion {
+    /// Parse a date (`2026-03-01` or an RFC 3339 timestamp) or a semantic
+    /// version (`2.4.1`, with or without a leading `v`).
+    pub fn parse(value: &str) -> Result<Self> {
+        let value = value.trim();
+        if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+            return Ok(Self::Date(date));
+        }
+        if let Ok(timestamp) = DateTime::parse_from_rfc3339(value) {
+            return Ok(Self::Date(timestamp.date_naive()));
+        }
+
+        let version = value.strip_prefix('v').unwrap_or(value);
+        semver::Version::parse(version)
+            .map(Self::Semver)
+            .map_err(|_| LicenseError::InvalidBuildVersion(value.to_string()))
+    }
+}
+
+impl FromStr for BuildVersion {
+    type Err = LicenseError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::parse(s)
+    }
+}
+
+impl fmt::Display for BuildVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Date(date) => write!(f, "{}", date),
+            Self::Semver(version) => write!(f, "{}", version),
+        }
+    }
+}